@@ -0,0 +1,67 @@
+//! Filtering 100k hosts: building the search, one query from scratch, and
+//! typing a query a char at a time, each run narrowing the last but where
+//! a segment grows long enough to forgive another typo:
+//! `cargo bench --bench filter`.
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use gossh::history::History;
+use gossh::list::List;
+use gossh::query::Query;
+use gossh::scorer::Fuzzy;
+use gossh::search::Search;
+use std::hint::black_box;
+
+const HOSTS: usize = 100_000;
+
+fn hosts() -> Vec<List> {
+    let roles = ["web", "db", "cache", "queue", "mail", "bastion", "api"];
+    let envs = ["prod", "staging", "dev"];
+    (0..HOSTS)
+        .map(|i| {
+            let alias = format!("{}-{}-{i:05}", roles[i % roles.len()], envs[i % envs.len()]);
+            List {
+                hostname: format!("10.{}.{}.{}", i >> 16, (i >> 8) & 0xff, i & 0xff),
+                display_name: alias.clone(),
+                alias,
+                user: "deploy".to_owned(),
+                tags: vec![envs[i % envs.len()].to_owned()],
+                ..List::default()
+            }
+        })
+        .collect()
+}
+
+fn filter(c: &mut Criterion) {
+    let hosts = hosts();
+    let history = History::default();
+    let mut group = c.benchmark_group("filter 100k");
+    group.sample_size(10);
+    group.bench_function("index", |b| {
+        b.iter(|| Search::new(black_box(&hosts), &Fuzzy, &history, 0))
+    });
+    let query = Query::parse("wbprd").unwrap();
+    group.bench_function("from scratch", |b| {
+        b.iter_batched_ref(
+            || Search::new(&hosts, &Fuzzy, &history, 0),
+            |search| search.run(black_box(&query)),
+            BatchSize::LargeInput,
+        )
+    });
+    let typed: Vec<Query> = (1..="web-prod-0".len())
+        .map(|n| Query::parse(&"web-prod-0"[..n]).unwrap())
+        .collect();
+    group.bench_function("typing", |b| {
+        b.iter_batched_ref(
+            || Search::new(&hosts, &Fuzzy, &history, 0),
+            |search| {
+                for query in &typed {
+                    black_box(search.run(query));
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, filter);
+criterion_main!(benches);
@@ -0,0 +1,33 @@
+//! How long each scorer takes to rank the test corpus for every corpus query:
+//! `cargo bench --bench scorers`.
+use criterion::{Criterion, criterion_group, criterion_main};
+use gossh::scorer::SCORERS;
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+
+fn corpus(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/corpus");
+    fs::read_to_string(path.join(name)).unwrap()
+}
+
+fn scorers(c: &mut Criterion) {
+    let hosts = corpus("hosts.txt");
+    let queries = corpus("queries.txt");
+    let mut group = c.benchmark_group("scorers");
+    for scorer in SCORERS {
+        group.bench_function(scorer.name(), |b| {
+            b.iter(|| {
+                for query in queries.lines() {
+                    for host in hosts.lines() {
+                        black_box(scorer.score(black_box(query), black_box(host)));
+                    }
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, scorers);
+criterion_main!(benches);
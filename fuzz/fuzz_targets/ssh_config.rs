@@ -0,0 +1,16 @@
+#![no_main]
+
+use gossh::list::Server;
+use gossh::ssh_config::Config;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|text: &str| {
+    let config = Config::parse(text);
+    let written = config.to_string();
+    assert_eq!(written, text);
+    assert_eq!(Config::parse(&written), config);
+
+    // includes are left out so the fuzzer never wanders the filesystem
+    let (_, blocks) = Server::parse_list(text).unwrap();
+    let _ = Server::hash_list(blocks);
+});
@@ -0,0 +1,130 @@
+//! What to do with several marked hosts at once: open each in tmux, print
+//! their aliases or run a command on every one.
+use std::env;
+use std::process::Command;
+
+/// An entry of the menu Enter opens when hosts are marked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choice {
+    Windows,
+    Panes,
+    Print,
+    /// Asks for the command to run before it is a `Batch`.
+    Run,
+}
+
+impl Choice {
+    pub fn label(self) -> &'static str {
+        match self {
+            Choice::Windows => "Open each in a new tmux window",
+            Choice::Panes => "Open each in a new tmux pane",
+            Choice::Print => "Print the aliases",
+            Choice::Run => "Run a command on each",
+        }
+    }
+    /// The choices on offer; the tmux ones only inside tmux.
+    pub fn offered(in_tmux: bool) -> Vec<Choice> {
+        let mut choices = Vec::new();
+        if in_tmux {
+            choices.extend([Choice::Windows, Choice::Panes]);
+        }
+        choices.extend([Choice::Print, Choice::Run]);
+        choices
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Batch {
+    Windows,
+    Panes,
+    Print,
+    /// Runs the command on each host in turn.
+    Run(String),
+}
+
+/// Whether gossh runs inside tmux, per `$TMUX`.
+pub fn in_tmux() -> bool {
+    env::var_os("TMUX").is_some_and(|value| !value.is_empty())
+}
+
+/// The commands carrying out `batch` on `aliases`, to be run in order;
+/// none for `Batch::Print`, which only writes to stdout.
+pub fn commands(batch: &Batch, aliases: &[&str]) -> Vec<Command> {
+    let tmux = |args: &[&str]| {
+        let mut command = Command::new("tmux");
+        command.args(args);
+        command
+    };
+    let mut commands = Vec::new();
+    match batch {
+        Batch::Windows => {
+            for alias in aliases {
+                commands.push(tmux(&["new-window", "-n", alias, "ssh", alias]));
+            }
+        }
+        Batch::Panes => {
+            for alias in aliases {
+                commands.push(tmux(&["split-window", "ssh", alias]));
+                // each split halves the newest pane; evening them out every
+                // time keeps tmux from running out of room for the next
+                commands.push(tmux(&["select-layout", "tiled"]));
+            }
+        }
+        Batch::Print => {}
+        Batch::Run(command) => {
+            for alias in aliases {
+                let mut ssh = Command::new("ssh");
+                ssh.args([alias, command.as_str()]);
+                commands.push(ssh);
+            }
+        }
+    }
+    commands
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lines(batch: &Batch) -> Vec<String> {
+        commands(batch, &["web1", "db"])
+            .iter()
+            .map(|command| {
+                let mut words = vec![command.get_program().to_string_lossy().into_owned()];
+                words.extend(command.get_args().map(|a| a.to_string_lossy().into_owned()));
+                words.join(" ")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn offers_tmux_only_inside_it() {
+        assert_eq!(Choice::offered(false), [Choice::Print, Choice::Run]);
+        assert_eq!(Choice::offered(true)[..2], [Choice::Windows, Choice::Panes]);
+    }
+
+    #[test]
+    fn builds_the_commands() {
+        assert_eq!(
+            lines(&Batch::Windows),
+            [
+                "tmux new-window -n web1 ssh web1",
+                "tmux new-window -n db ssh db"
+            ]
+        );
+        assert_eq!(
+            lines(&Batch::Panes),
+            [
+                "tmux split-window ssh web1",
+                "tmux select-layout tiled",
+                "tmux split-window ssh db",
+                "tmux select-layout tiled"
+            ]
+        );
+        assert!(lines(&Batch::Print).is_empty());
+        assert_eq!(
+            lines(&Batch::Run("uptime -p".to_owned())),
+            ["ssh web1 uptime -p", "ssh db uptime -p"]
+        );
+    }
+}
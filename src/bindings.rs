@@ -0,0 +1,416 @@
+//! What the keys do in the picker. Keys are bound to actions per mode;
+//! a key bound to nothing is typed into the search box in insert mode.
+//! The `vim` preset adds a normal mode where letters move instead.
+use crate::table::Column;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::BTreeMap;
+
+/// The preset used when the config doesn't name one.
+pub const DEFAULT: &str = "default";
+pub const PRESETS: &[&str] = &["default", "emacs", "vim"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Keys type into the search box unless bound.
+    Insert,
+    /// Only bound keys do anything; the `vim` preset's mode for moving.
+    Normal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+    /// Connects to the selected host, or offers what to do with the
+    /// marked ones.
+    Accept,
+    Cancel,
+    TogglePreview,
+    PreviewUp,
+    PreviewDown,
+    /// Sorts by the next shown column, see `table::next_sort`.
+    Sort,
+    ToggleColumn(Column),
+    /// Marks or unmarks the selected host and moves down.
+    ToggleMark,
+    NormalMode,
+    InsertMode,
+}
+
+impl Action {
+    /// Every action but the column toggles, which take a column.
+    const SIMPLE: [Action; 15] = [
+        Action::Up,
+        Action::Down,
+        Action::PageUp,
+        Action::PageDown,
+        Action::First,
+        Action::Last,
+        Action::Accept,
+        Action::Cancel,
+        Action::TogglePreview,
+        Action::PreviewUp,
+        Action::PreviewDown,
+        Action::Sort,
+        Action::ToggleMark,
+        Action::NormalMode,
+        Action::InsertMode,
+    ];
+    pub fn name(self) -> String {
+        let name = match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::PageUp => "page-up",
+            Action::PageDown => "page-down",
+            Action::First => "first",
+            Action::Last => "last",
+            Action::Accept => "accept",
+            Action::Cancel => "cancel",
+            Action::TogglePreview => "toggle-preview",
+            Action::PreviewUp => "preview-up",
+            Action::PreviewDown => "preview-down",
+            Action::Sort => "sort",
+            Action::ToggleMark => "toggle-mark",
+            Action::NormalMode => "normal-mode",
+            Action::InsertMode => "insert-mode",
+            Action::ToggleColumn(column) => return format!("toggle-{}", column.name()),
+        };
+        name.to_owned()
+    }
+    pub fn by_name(name: &str) -> Option<Action> {
+        if let Some(column) = name.strip_prefix("toggle-").and_then(Column::by_name) {
+            return Some(Action::ToggleColumn(column));
+        }
+        Action::SIMPLE.into_iter().find(|a| a.name() == name)
+    }
+    /// Every action's name, the column toggles as `toggle-COLUMN`.
+    fn names() -> Vec<String> {
+        let mut names: Vec<String> = Action::SIMPLE.iter().map(|a| a.name()).collect();
+        names.push("toggle-COLUMN".to_owned());
+        names
+    }
+}
+
+/// A key with its modifiers, as written in the config: `ctrl-n`,
+/// `alt-shift-tab`, `pagedown`, `G`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub fn parse(text: &str) -> Result<Key, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text;
+        loop {
+            let lower = rest.to_ascii_lowercase();
+            let modifier = [
+                ("ctrl-", KeyModifiers::CONTROL),
+                ("alt-", KeyModifiers::ALT),
+                ("shift-", KeyModifiers::SHIFT),
+            ]
+            .into_iter()
+            .find(|(prefix, _)| lower.starts_with(prefix) && rest.len() > prefix.len());
+            let Some((prefix, modifier)) = modifier else {
+                break;
+            };
+            modifiers |= modifier;
+            rest = &rest[prefix.len()..];
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "space" => KeyCode::Char(' '),
+                f => match f.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key {text:?}")),
+                },
+            },
+        };
+        Ok(Key { code, modifiers }.normalized())
+    }
+    /// Shift is part of the char for chars (`G`, not `shift-g`), so it is
+    /// left out when comparing them.
+    fn normalized(self) -> Key {
+        match self.code {
+            KeyCode::Char(_) => Key {
+                modifiers: self.modifiers - KeyModifiers::SHIFT,
+                ..self
+            },
+            _ => self,
+        }
+    }
+    fn of(event: &KeyEvent) -> Key {
+        Key {
+            code: event.code,
+            modifiers: event.modifiers,
+        }
+        .normalized()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+    insert: Vec<(Key, Action)>,
+    normal: Vec<(Key, Action)>,
+}
+
+fn keys(bindings: &[(&str, Action)]) -> Vec<(Key, Action)> {
+    bindings
+        .iter()
+        .map(|(key, action)| (Key::parse(key).expect("preset keys parse"), *action))
+        .collect()
+}
+
+/// The keys every preset starts from in insert mode.
+fn common() -> Vec<(&'static str, Action)> {
+    let mut bindings = vec![
+        ("ctrl-c", Action::Cancel),
+        ("ctrl-d", Action::Cancel),
+        ("esc", Action::Cancel),
+        ("enter", Action::Accept),
+        ("up", Action::Up),
+        ("down", Action::Down),
+        ("pageup", Action::PageUp),
+        ("pagedown", Action::PageDown),
+        ("home", Action::First),
+        ("end", Action::Last),
+        ("alt-p", Action::TogglePreview),
+        ("alt-up", Action::PreviewUp),
+        ("alt-down", Action::PreviewDown),
+        ("alt-s", Action::Sort),
+        ("tab", Action::ToggleMark),
+    ];
+    const ALT_DIGITS: [&str; 8] = [
+        "alt-1", "alt-2", "alt-3", "alt-4", "alt-5", "alt-6", "alt-7", "alt-8",
+    ];
+    for (key, column) in ALT_DIGITS.into_iter().zip(Column::ALL) {
+        bindings.push((key, Action::ToggleColumn(column)));
+    }
+    bindings
+}
+
+impl Bindings {
+    pub fn preset(name: &str) -> Option<Bindings> {
+        let mut insert = common();
+        let mut normal = Vec::new();
+        match name {
+            "default" => {}
+            "emacs" => insert.extend([
+                ("ctrl-n", Action::Down),
+                ("ctrl-p", Action::Up),
+                ("ctrl-j", Action::Down),
+                ("ctrl-k", Action::Up),
+                ("ctrl-v", Action::PageDown),
+                ("alt-v", Action::PageUp),
+                ("alt-<", Action::First),
+                ("alt->", Action::Last),
+                ("ctrl-g", Action::Cancel),
+            ]),
+            "vim" => {
+                // Esc leaves insert mode instead of the picker
+                insert.retain(|(key, _)| *key != "esc");
+                insert.push(("esc", Action::NormalMode));
+                normal = vec![
+                    ("esc", Action::Cancel),
+                    ("q", Action::Cancel),
+                    ("ctrl-c", Action::Cancel),
+                    ("enter", Action::Accept),
+                    ("j", Action::Down),
+                    ("k", Action::Up),
+                    ("down", Action::Down),
+                    ("up", Action::Up),
+                    ("ctrl-d", Action::PageDown),
+                    ("ctrl-u", Action::PageUp),
+                    ("ctrl-f", Action::PageDown),
+                    ("ctrl-b", Action::PageUp),
+                    ("g", Action::First),
+                    ("G", Action::Last),
+                    ("i", Action::InsertMode),
+                    ("a", Action::InsertMode),
+                    ("/", Action::InsertMode),
+                    ("p", Action::TogglePreview),
+                    ("ctrl-e", Action::PreviewDown),
+                    ("ctrl-y", Action::PreviewUp),
+                    ("s", Action::Sort),
+                    ("tab", Action::ToggleMark),
+                    ("space", Action::ToggleMark),
+                ];
+                let digits = ["1", "2", "3", "4", "5", "6", "7", "8"];
+                for (key, column) in digits.into_iter().zip(Column::ALL) {
+                    normal.push((key, Action::ToggleColumn(column)));
+                }
+            }
+            _ => return None,
+        }
+        Some(Bindings {
+            insert: keys(&insert),
+            normal: keys(&normal),
+        })
+    }
+    /// Binds `key` to `action` in `mode`, or unbinds it for `None`.
+    pub fn bind(&mut self, mode: Mode, key: Key, action: Option<Action>) {
+        let bindings = match mode {
+            Mode::Insert => &mut self.insert,
+            Mode::Normal => &mut self.normal,
+        };
+        bindings.retain(|(bound, _)| *bound != key);
+        if let Some(action) = action {
+            bindings.push((key, action));
+        }
+    }
+    /// Applies the `key = "action"` pairs of a config to `mode`; the action
+    /// `none` unbinds a key.
+    pub fn apply(&mut self, mode: Mode, pairs: &BTreeMap<String, String>) -> Result<(), String> {
+        for (key, action) in pairs {
+            let key = Key::parse(key)?;
+            let action = match action.as_str() {
+                "none" => None,
+                name => Some(Action::by_name(name).ok_or_else(|| {
+                    format!(
+                        "unknown action {name:?}, expected one of: none, {}",
+                        Action::names().join(", ")
+                    )
+                })?),
+            };
+            self.bind(mode, key, action);
+        }
+        Ok(())
+    }
+    /// Whether there is a normal mode to switch to.
+    pub fn is_modal(&self) -> bool {
+        !self.normal.is_empty()
+    }
+    pub fn action(&self, mode: Mode, event: &KeyEvent) -> Option<Action> {
+        let bindings = match mode {
+            Mode::Insert => &self.insert,
+            Mode::Normal => &self.normal,
+        };
+        let key = Key::of(event);
+        bindings
+            .iter()
+            .find(|(bound, _)| *bound == key)
+            .map(|(_, action)| *action)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn event(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        event(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    fn plain(c: char) -> KeyEvent {
+        event(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn parses_keys() {
+        let key = |text| Key::parse(text).unwrap();
+        assert_eq!(key("ctrl-n"), Key::of(&ctrl('n')));
+        assert_eq!(
+            key("Ctrl-Alt-PageDown").modifiers,
+            KeyModifiers::CONTROL | KeyModifiers::ALT
+        );
+        assert_eq!(key("ctrl--"), Key::of(&ctrl('-')));
+        assert_eq!(key("f5").code, KeyCode::F(5));
+        assert_eq!(key("space").code, KeyCode::Char(' '));
+        // crossterm reports `G` with shift held
+        assert_eq!(
+            key("G"),
+            Key::of(&event(KeyCode::Char('G'), KeyModifiers::SHIFT))
+        );
+        assert!(Key::parse("hyper-x").is_err());
+        assert!(Key::parse("f13").is_err());
+    }
+
+    #[test]
+    fn presets() {
+        for name in PRESETS {
+            assert!(Bindings::preset(name).is_some(), "{name}");
+        }
+        assert!(Bindings::preset("nano").is_none());
+
+        let default = Bindings::preset("default").unwrap();
+        assert_eq!(default.action(Mode::Insert, &ctrl('n')), None);
+        assert_eq!(default.action(Mode::Insert, &plain('j')), None);
+        let alt_3 = event(KeyCode::Char('3'), KeyModifiers::ALT);
+        assert_eq!(
+            default.action(Mode::Insert, &alt_3),
+            Some(Action::ToggleColumn(Column::HostName))
+        );
+
+        let emacs = Bindings::preset("emacs").unwrap();
+        assert_eq!(emacs.action(Mode::Insert, &ctrl('n')), Some(Action::Down));
+        assert_eq!(emacs.action(Mode::Insert, &ctrl('k')), Some(Action::Up));
+        assert!(!emacs.is_modal());
+
+        let vim = Bindings::preset("vim").unwrap();
+        let esc = event(KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(vim.action(Mode::Insert, &esc), Some(Action::NormalMode));
+        assert_eq!(vim.action(Mode::Insert, &plain('j')), None);
+        assert_eq!(vim.action(Mode::Normal, &plain('j')), Some(Action::Down));
+        assert_eq!(
+            vim.action(Mode::Normal, &plain('i')),
+            Some(Action::InsertMode)
+        );
+        assert!(vim.is_modal());
+    }
+
+    #[test]
+    fn config_overrides() {
+        let mut bindings = Bindings::preset("default").unwrap();
+        let pairs: BTreeMap<String, String> = [
+            ("ctrl-o", "toggle-preview"),
+            ("esc", "none"),
+            ("ctrl-t", "toggle-tags"),
+        ]
+        .into_iter()
+        .map(|(k, a)| (k.to_owned(), a.to_owned()))
+        .collect();
+        bindings.apply(Mode::Insert, &pairs).unwrap();
+        assert_eq!(
+            bindings.action(Mode::Insert, &ctrl('o')),
+            Some(Action::TogglePreview)
+        );
+        assert_eq!(
+            bindings.action(Mode::Insert, &ctrl('t')),
+            Some(Action::ToggleColumn(Column::Tags))
+        );
+        let esc = event(KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(bindings.action(Mode::Insert, &esc), None);
+
+        let bad: BTreeMap<String, String> = [("ctrl-x".to_owned(), "explode".to_owned())].into();
+        let error = bindings.apply(Mode::Insert, &bad).unwrap_err();
+        assert!(error.contains("toggle-preview"), "{error}");
+        assert!(!error.contains("toggle-name"), "{error}");
+        assert!(error.ends_with("insert-mode, toggle-COLUMN"), "{error}");
+    }
+}
@@ -0,0 +1,164 @@
+use crate::list::{List, expand_tilde};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+// bump whenever parsing changes what ends up in the host list
+const CACHE_VERSION: u32 = 7;
+
+/// Fingerprint of one file (or globbed directory) the host list was built from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Source {
+    pub path: PathBuf,
+    pub mtime: Option<(u64, u32)>,
+    pub size: Option<u64>,
+}
+
+impl Source {
+    /// A missing path is recorded too, so creating it later invalidates the cache.
+    pub fn stat(path: &Path) -> Source {
+        let meta = fs::metadata(path).ok();
+        Source {
+            path: path.to_path_buf(),
+            mtime: meta
+                .as_ref()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| (d.as_secs(), d.subsec_nanos())),
+            size: meta.map(|m| m.len()),
+        }
+    }
+    pub fn is_fresh(&self) -> bool {
+        Source::stat(&self.path) == *self
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Cache {
+    version: u32,
+    /// The config the host list was read from, includes aside.
+    config: PathBuf,
+    sources: Vec<Source>,
+    hosts: Vec<List>,
+}
+
+pub fn cache_path() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            env::var_os("HOME")?;
+            expand_tilde("~/.cache")
+        }
+    };
+    Some(dir.join("gossh").join("hosts.json"))
+}
+
+/// Returns the cached host list for `config`, or `None` when the cache is
+/// missing, unreadable, from another version, built from another config or
+/// any of its sources changed.
+pub fn load(path: &Path, config: &Path) -> Option<Vec<List>> {
+    let data = fs::read(path).ok()?;
+    let cache: Cache = serde_json::from_slice(&data).ok()?;
+    if cache.version != CACHE_VERSION
+        || cache.config != config
+        || !cache.sources.iter().all(Source::is_fresh)
+    {
+        return None;
+    }
+    Some(cache.hosts)
+}
+
+pub fn store(path: &Path, config: &Path, sources: &[Source], hosts: &[List]) -> io::Result<()> {
+    let cache = Cache {
+        version: CACHE_VERSION,
+        config: config.to_path_buf(),
+        sources: sources.to_vec(),
+        hosts: hosts.to_vec(),
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // write then rename so a concurrent start never reads half a cache
+    let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+    fs::write(&tmp, serde_json::to_vec(&cache)?)?;
+    fs::rename(tmp, path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::list::Server;
+
+    fn write(path: &Path, text: &str) {
+        fs::write(path, text).unwrap();
+    }
+
+    #[test]
+    fn warm_start_reads_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config");
+        let cache = dir.path().join("cache").join("hosts.json");
+        write(&config, "Host web1\n  HostName web1.example.com\n");
+
+        let cold = Server::load_from(&config, Some(&cache));
+        assert_eq!(load(&cache, &config), Some(cold.clone()));
+        assert_eq!(Server::load_from(&config, Some(&cache)), cold);
+    }
+
+    #[test]
+    fn changed_source_invalidates() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config");
+        let included = dir.path().join("extra");
+        let cache = dir.path().join("hosts.json");
+        write(&config, "Include extra\n");
+        write(&included, "Host db1\n  HostName db1.example.com\n");
+
+        assert_eq!(Server::load_from(&config, Some(&cache)).len(), 1);
+        write(
+            &included,
+            "Host db1\n  HostName db1.example.com\nHost db2\n  HostName db2.example.com\n",
+        );
+        assert_eq!(load(&cache, &config), None);
+        assert_eq!(Server::load_from(&config, Some(&cache)).len(), 2);
+    }
+
+    #[test]
+    fn new_globbed_file_invalidates() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config");
+        let conf_d = dir.path().join("conf.d");
+        let cache = dir.path().join("hosts.json");
+        fs::create_dir(&conf_d).unwrap();
+        write(&config, "Include conf.d/*.conf\n");
+        write(&conf_d.join("a.conf"), "Host a\n  HostName a.example.com\n");
+
+        assert_eq!(Server::load_from(&config, Some(&cache)).len(), 1);
+        write(&conf_d.join("b.conf"), "Host b\n  HostName b.example.com\n");
+        assert_eq!(load(&cache, &config), None);
+        assert_eq!(Server::load_from(&config, Some(&cache)).len(), 2);
+    }
+
+    #[test]
+    fn another_config_misses() {
+        let dir = tempfile::tempdir().unwrap();
+        let (work, home) = (dir.path().join("work"), dir.path().join("home"));
+        let cache = dir.path().join("hosts.json");
+        write(&work, "Host build\n  HostName build.example.com\n");
+        write(&home, "Host nas\n  HostName nas.lan\n");
+
+        let aliases = |config: &Path| -> Vec<String> {
+            Server::load_from(config, Some(&cache))
+                .into_iter()
+                .map(|host| host.alias)
+                .collect()
+        };
+        assert_eq!(aliases(&work), ["build"]);
+        assert_eq!(load(&cache, &home), None);
+        assert_eq!(aliases(&home), ["nas"]);
+        assert_eq!(aliases(&work), ["build"]);
+    }
+}
@@ -0,0 +1,85 @@
+//! Command line flags. Anything that isn't a flag is the initial query.
+
+pub const USAGE: &str = "\
+usage: gossh [options] [query...]
+
+options:
+  --scorer NAME   rank hosts with NAME instead of the configured scorer
+  -1, --select-1  connect right away when one host matches, or one's alias is the query
+  -0, --exit-0    exit with an error instead of opening the picker when nothing matches
+  -h, --help      print this help
+";
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Args {
+    pub query: Option<String>,
+    pub scorer: Option<String>,
+    pub select_1: bool,
+    pub exit_0: bool,
+    pub help: bool,
+}
+
+impl Args {
+    /// Parses the arguments after the program name.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut words: Vec<String> = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => parsed.help = true,
+                "-1" | "--select-1" => parsed.select_1 = true,
+                "-0" | "--exit-0" => parsed.exit_0 = true,
+                "--scorer" => {
+                    let name = args.next().ok_or("--scorer needs a name")?;
+                    parsed.scorer = Some(name);
+                }
+                // everything after `--` is query, even if it looks like a flag
+                "--" => words.extend(args.by_ref()),
+                _ => {
+                    if let Some(name) = arg.strip_prefix("--scorer=") {
+                        parsed.scorer = Some(name.to_owned());
+                    } else if arg.starts_with('-') && arg.len() > 1 {
+                        return Err(format!("unknown option {arg}"));
+                    } else {
+                        words.push(arg);
+                    }
+                }
+            }
+        }
+        if !words.is_empty() {
+            parsed.query = Some(words.join(" "));
+        }
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn flags_and_query() {
+        let args = parse(&["--scorer", "segment", "web", "prod"]).unwrap();
+        assert_eq!(args.scorer.as_deref(), Some("segment"));
+        assert_eq!(args.query.as_deref(), Some("web prod"));
+        let args = parse(&["--scorer=levenshtein"]).unwrap();
+        assert_eq!(args.scorer.as_deref(), Some("levenshtein"));
+        assert_eq!(args.query, None);
+        assert_eq!(parse(&["--", "-x"]).unwrap().query.as_deref(), Some("-x"));
+        assert!(parse(&["-h"]).unwrap().help);
+        let args = parse(&["-1", "--exit-0", "web1"]).unwrap();
+        assert!(args.select_1 && args.exit_0);
+        assert_eq!(args.query.as_deref(), Some("web1"));
+    }
+
+    #[test]
+    fn rejects_bad_flags() {
+        assert!(parse(&["--scorer"]).is_err());
+        assert!(parse(&["--frobnicate"]).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+//! Settings from `$XDG_CONFIG_HOME/gossh/config.toml`; every key is optional.
+use crate::bindings::{self, Bindings, Mode};
+use crate::layout::{self, Layout, Layouts};
+use crate::list::expand_tilde;
+use crate::navigation::{self, Navigation};
+use crate::table::{self, Column};
+use crate::theme::{self, Depth, Theme};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Name of the scorer search words are ranked with, see `scorer::names`.
+    pub scorer: Option<String>,
+    /// Layouts the search box folds back to QWERTY, see `layout::DEFAULT`.
+    pub layouts: Option<Vec<String>>,
+    /// User-defined layouts by name, usable in `layouts`.
+    pub keymaps: BTreeMap<String, Keymap>,
+    /// Columns of the host table, in order, see `table::DEFAULT`.
+    pub columns: Option<Vec<String>>,
+    /// Name of the theme, built in or from `themes`, see `theme::DEFAULT`.
+    pub theme: Option<String>,
+    /// User-defined themes by name.
+    pub themes: BTreeMap<String, ThemeColors>,
+    /// What the keys do in the picker.
+    pub keys: KeyConfig,
+    /// Whether moving past the first or last host goes round to the other.
+    pub wrap: bool,
+    /// Rows kept in view around the selection, see `navigation::SCROLL_OFF`.
+    #[serde(rename = "scroll-off")]
+    pub scroll_off: Option<usize>,
+}
+
+/// A preset, see `bindings::PRESETS`, and `key = "action"` pairs changing
+/// it per mode; the action `none` unbinds a key.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyConfig {
+    pub preset: Option<String>,
+    pub insert: BTreeMap<String, String>,
+    pub normal: BTreeMap<String, String>,
+}
+
+/// A theme's colours as `#rrggbb`; those left out are the `base` theme's,
+/// a built-in one.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeColors {
+    pub base: Option<String>,
+    pub text: Option<String>,
+    pub background: Option<String>,
+    pub selected: Option<String>,
+    pub accent: Option<String>,
+    pub reading: Option<String>,
+    pub error: Option<String>,
+    pub suggestion: Option<String>,
+    pub marked: Option<String>,
+}
+
+fn builtin_theme(name: &str) -> Option<Theme> {
+    theme::builtin().into_iter().find(|t| t.name == name)
+}
+
+impl ThemeColors {
+    fn theme(&self, name: &str) -> Result<Theme, String> {
+        let base = self.base.as_deref().unwrap_or(theme::DEFAULT);
+        let mut theme = builtin_theme(base).ok_or_else(|| {
+            let names: Vec<String> = theme::builtin().into_iter().map(|t| t.name).collect();
+            format!(
+                "theme `{name}` is based on unknown theme {base:?}, expected one of: {}",
+                names.join(", ")
+            )
+        })?;
+        theme.name = name.to_owned();
+        let colors = [
+            (&mut theme.text, &self.text),
+            (&mut theme.background, &self.background),
+            (&mut theme.selected, &self.selected),
+            (&mut theme.accent, &self.accent),
+            (&mut theme.reading, &self.reading),
+            (&mut theme.error, &self.error),
+            (&mut theme.suggestion, &self.suggestion),
+            (&mut theme.marked, &self.marked),
+        ];
+        for (color, value) in colors {
+            if let Some(value) = value {
+                *color = theme::parse_rgb(value).map_err(|e| format!("theme `{name}`: {e}"))?;
+            }
+        }
+        Ok(theme)
+    }
+}
+
+/// `from` is what the keys type in the layout, `to` what they type on QWERTY.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Keymap {
+    pub from: String,
+    pub to: String,
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        let dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => {
+                env::var_os("HOME")?;
+                expand_tilde("~/.config")
+            }
+        };
+        Some(dir.join("gossh").join("config.toml"))
+    }
+    /// Reads the config at `path`. A missing file is the default config;
+    /// one that can't be read or parsed is an error worth reporting.
+    pub fn load(path: &Path) -> io::Result<Config> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(e),
+        };
+        Config::parse(&text)
+    }
+    pub fn parse(text: &str) -> io::Result<Config> {
+        toml::from_str(text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.message()))
+    }
+    /// The selected layouts, with the keymaps defined here taking part.
+    pub fn layouts(&self) -> Result<Layouts, String> {
+        let custom = self
+            .keymaps
+            .iter()
+            .map(|(name, keymap)| Layout::new(name, &keymap.from, &keymap.to))
+            .collect::<Result<_, _>>()?;
+        match &self.layouts {
+            Some(names) => {
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                Layouts::select(&names, custom)
+            }
+            None => Layouts::select(layout::DEFAULT, custom),
+        }
+    }
+    /// The selected theme, for a terminal showing `depth` colours. A theme
+    /// defined here replaces a built-in one of the same name.
+    pub fn theme(&self, depth: Depth) -> Result<Theme, String> {
+        let name = self.theme.as_deref().unwrap_or(theme::DEFAULT);
+        let mut theme = match self.themes.get(name) {
+            Some(colors) => colors.theme(name)?,
+            None => builtin_theme(name).ok_or_else(|| {
+                let mut names: Vec<String> = theme::builtin().into_iter().map(|t| t.name).collect();
+                names.extend(self.themes.keys().cloned());
+                format!(
+                    "unknown theme {name:?}, expected one of: {}",
+                    names.join(", ")
+                )
+            })?,
+        };
+        theme.depth = depth;
+        Ok(theme)
+    }
+    /// The selected key bindings with the changes made here.
+    pub fn bindings(&self) -> Result<Bindings, String> {
+        let name = self.keys.preset.as_deref().unwrap_or(bindings::DEFAULT);
+        let mut bindings = Bindings::preset(name).ok_or_else(|| {
+            format!(
+                "unknown key preset {name:?}, expected one of: {}",
+                bindings::PRESETS.join(", ")
+            )
+        })?;
+        bindings.apply(Mode::Insert, &self.keys.insert)?;
+        bindings.apply(Mode::Normal, &self.keys.normal)?;
+        Ok(bindings)
+    }
+    pub fn navigation(&self) -> Navigation {
+        Navigation {
+            wrap: self.wrap,
+            scroll_off: self.scroll_off.unwrap_or(navigation::SCROLL_OFF),
+        }
+    }
+    /// The host table's columns.
+    pub fn columns(&self) -> Result<Vec<Column>, String> {
+        let Some(names) = &self.columns else {
+            return Ok(table::DEFAULT.to_vec());
+        };
+        if names.is_empty() {
+            return Err("`columns` needs at least one column".to_owned());
+        }
+        names
+            .iter()
+            .map(|name| {
+                Column::by_name(name).ok_or_else(|| {
+                    let names: Vec<&str> = Column::ALL.iter().map(|c| c.name()).collect();
+                    format!(
+                        "unknown column {name:?}, expected one of: {}",
+                        names.join(", ")
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_and_defaults() {
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+        let config = Config::parse("scorer = \"jaro-winkler\"\n").unwrap();
+        assert_eq!(config.scorer.as_deref(), Some("jaro-winkler"));
+        assert!(Config::parse("scorer = 3").is_err());
+        assert!(Config::parse("scorrer = \"fuzzy\"").is_err());
+        let config = Config::parse("wrap = true\nscroll-off = 0").unwrap();
+        assert_eq!(
+            config.navigation(),
+            Navigation {
+                wrap: true,
+                scroll_off: 0
+            }
+        );
+        assert_eq!(Config::default().navigation(), Navigation::default());
+    }
+
+    #[test]
+    fn user_keymaps() {
+        let config = Config::parse(
+            "layouts = [\"workman\", \"russian\"]\n\
+             [keymaps.workman]\n\
+             from = \"qdrwbjfup\"\n\
+             to = \"qwertyuio\"\n",
+        )
+        .unwrap();
+        let layouts = config.layouts().unwrap();
+        assert_eq!(layouts.translations("dwb"), [("workman", "wrt".to_owned())]);
+        assert_eq!(layouts.translations("цуи"), [("russian", "web".to_owned())]);
+        let config = Config::parse("layouts = [\"workman\"]").unwrap();
+        assert!(config.layouts().is_err());
+    }
+
+    #[test]
+    fn columns() {
+        assert_eq!(Config::default().columns().unwrap(), table::DEFAULT);
+        let config = Config::parse("columns = [\"alias\", \"last-connected\"]").unwrap();
+        assert_eq!(
+            config.columns().unwrap(),
+            [Column::Alias, Column::LastConnected]
+        );
+        assert!(Config::parse("columns = []").unwrap().columns().is_err());
+        let error = Config::parse("columns = [\"colour\"]")
+            .unwrap()
+            .columns()
+            .unwrap_err();
+        assert!(error.contains("alias, hostname"), "{error}");
+    }
+
+    #[test]
+    fn themes() {
+        let theme = Config::default().theme(Depth::Ansi256).unwrap();
+        assert_eq!((theme.name.as_str(), theme.depth), ("dark", Depth::Ansi256));
+        let config = Config::parse("theme = \"light\"").unwrap();
+        assert_eq!(config.theme(Depth::None).unwrap().name, "light");
+
+        let config = Config::parse(
+            "theme = \"mine\"\n\
+             [themes.mine]\n\
+             base = \"light\"\n\
+             accent = \"#ff0000\"\n",
+        )
+        .unwrap();
+        let theme = config.theme(Depth::TrueColor).unwrap();
+        let light = builtin_theme("light").unwrap();
+        assert_eq!((theme.accent, theme.text), ((255, 0, 0), light.text));
+
+        let error = Config::parse("theme = \"neon\"\n[themes.mine]")
+            .unwrap()
+            .theme(Depth::TrueColor)
+            .unwrap_err();
+        assert!(error.contains("dark, light, mine"), "{error}");
+        let config = Config::parse("theme = \"x\"\n[themes.x]\ntext = \"red\"").unwrap();
+        assert!(config.theme(Depth::TrueColor).is_err());
+        assert!(Config::parse("[themes.x]\ncolour = \"#000000\"").is_err());
+    }
+
+    #[test]
+    fn keys() {
+        use crate::bindings::Action;
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+        let config = Config::parse(
+            "[keys]\n\
+             preset = \"vim\"\n\
+             [keys.normal]\n\
+             x = \"accept\"\n",
+        )
+        .unwrap();
+        let bindings = config.bindings().unwrap();
+        let x = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE);
+        assert_eq!(bindings.action(Mode::Normal, &x), Some(Action::Accept));
+        assert_eq!(bindings.action(Mode::Insert, &x), None);
+        assert!(bindings.is_modal());
+
+        let error = Config::parse("[keys]\npreset = \"nano\"")
+            .unwrap()
+            .bindings()
+            .unwrap_err();
+        assert!(error.contains("default, emacs, vim"), "{error}");
+        let config = Config::parse("[keys.insert]\n\"ctrl-\" = \"up\"").unwrap();
+        assert!(config.bindings().is_err());
+        assert!(Config::parse("[keys]\nvisual = {}").is_err());
+    }
+
+    #[test]
+    fn missing_file_is_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::load(&dir.path().join("config.toml")).unwrap();
+        assert_eq!(config, Config::default());
+    }
+}
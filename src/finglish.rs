@@ -0,0 +1,138 @@
+//! Phonetic transliteration of Persian into Finglish, the Latin spelling
+//! hosts get named in: `یوز` reads as `yooz` and `پیروز` as `piruz`. Short
+//! vowels aren't written in Persian; the fuzzy matcher doesn't need them.
+
+/// Spellings kept per query, each one a search of its own.
+const MAX_CANDIDATES: usize = 8;
+
+fn is_letter(c: char) -> bool {
+    spellings(c, false, false).is_some()
+}
+
+/// Persian and Arabic-Indic digits as ASCII ones.
+fn digit(c: char) -> Option<char> {
+    let value = match c {
+        '۰'..='۹' => c as u32 - '۰' as u32,
+        '٠'..='٩' => c as u32 - '٠' as u32,
+        _ => return None,
+    };
+    char::from_digit(value, 10)
+}
+
+/// The ways `c` is spelled in Finglish, likeliest first, or `None` when
+/// it isn't Persian. `start` and `end` tell where in its word it is.
+fn spellings(c: char, start: bool, end: bool) -> Option<&'static [&'static str]> {
+    let spellings: &[&str] = match c {
+        'ا' | 'أ' => &["a"],
+        'آ' => &["a", "aa"],
+        'ب' => &["b"],
+        'پ' => &["p"],
+        'ت' | 'ط' => &["t"],
+        'ث' | 'س' | 'ص' => &["s"],
+        'ج' => &["j"],
+        'چ' => &["ch"],
+        'ح' => &["h"],
+        'خ' => &["kh", "x"],
+        'د' => &["d"],
+        'ذ' | 'ز' | 'ض' | 'ظ' => &["z"],
+        'ر' => &["r"],
+        'ژ' => &["zh", "j"],
+        'ش' => &["sh"],
+        'ع' => &["a", "", "e"],
+        'غ' | 'ق' => &["gh", "q"],
+        'ف' => &["f"],
+        'ک' | 'ك' => &["k"],
+        'گ' => &["g"],
+        'ل' => &["l"],
+        'م' => &["m"],
+        'ن' => &["n"],
+        'و' if start => &["v"],
+        'و' => &["oo", "u", "o", "ou", "v"],
+        'ی' | 'ي' | 'ى' if start => &["y"],
+        'ی' | 'ي' | 'ى' if end => &["i", "y"],
+        'ی' | 'ي' | 'ى' => &["i", "y", "ee"],
+        'ه' if end => &["e", "h"],
+        'ه' => &["h"],
+        'ة' => &["e", "at"],
+        'إ' => &["e", "a"],
+        'ؤ' => &["o"],
+        'ئ' => &["", "y"],
+        '\u{64e}' => &["a"],
+        '\u{650}' => &["e"],
+        '\u{64f}' => &["o"],
+        // hamza, tatweel, shadda, sukun and ZWNJ aren't spelled
+        'ء' | 'ـ' | '\u{651}' | '\u{652}' | '\u{200c}' => &[""],
+        _ => return None,
+    };
+    Some(spellings)
+}
+
+/// Likely Finglish spellings of `text`, best first; the text around
+/// Persian words is kept. Empty when there's nothing Persian in it.
+pub fn romanize(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    if !chars.iter().any(|&c| is_letter(c)) {
+        return Vec::new();
+    }
+    // best first by the sum of each char's spelling rank; keeping only the
+    // best partial spellings at every step still finds the best in total
+    let mut candidates: Vec<(usize, String)> = vec![(0, String::new())];
+    for (i, &c) in chars.iter().enumerate() {
+        let start = i == 0 || !is_letter(chars[i - 1]);
+        let end = i + 1 == chars.len() || !is_letter(chars[i + 1]);
+        let Some(options) = spellings(c, start, end) else {
+            let c = digit(c).unwrap_or(c);
+            candidates.iter_mut().for_each(|(_, s)| s.push(c));
+            continue;
+        };
+        candidates = candidates
+            .iter()
+            .flat_map(|(cost, s)| {
+                options
+                    .iter()
+                    .enumerate()
+                    .map(move |(rank, option)| (cost + rank, format!("{s}{option}")))
+            })
+            .collect();
+        candidates.sort_by_key(|(cost, _)| *cost);
+        candidates.truncate(MAX_CANDIDATES);
+    }
+    let mut spelled: Vec<String> = Vec::new();
+    for (_, candidate) in candidates {
+        if !spelled.contains(&candidate) {
+            spelled.push(candidate);
+        }
+    }
+    spelled
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn spells_names() {
+        assert_eq!(romanize("یوز")[0], "yooz");
+        assert!(romanize("پیروز").contains(&"piruz".to_owned()));
+        assert!(romanize("خانه").contains(&"khane".to_owned()));
+        assert_eq!(romanize("علی")[0], "ali");
+        assert_eq!(romanize("وب۱")[0], "vb1");
+    }
+
+    #[test]
+    fn keeps_the_rest_of_the_query() {
+        assert_eq!(romanize("tag:prod یوز")[0], "tag:prod yooz");
+        assert!(romanize("web-prod").is_empty());
+        assert!(romanize("۱۲").is_empty());
+    }
+
+    #[test]
+    fn candidates_are_bounded_and_unique() {
+        let spelled = romanize("پیروز غیاثی");
+        assert!(spelled.len() <= MAX_CANDIDATES);
+        let mut unique = spelled.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), spelled.len());
+    }
+}
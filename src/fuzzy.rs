@@ -0,0 +1,275 @@
+//! fzf-style subsequence matching: `wbprd` matches `web-prod`, and matches
+//! that land on word starts, camelCase humps or consecutive runs score higher.
+use crate::normalize::fold_case;
+use std::cell::RefCell;
+
+const SCORE_MATCH: i32 = 16;
+const GAP_START: i32 = 3;
+const GAP_EXTENSION: i32 = 1;
+const BONUS_BOUNDARY: i32 = 8;
+const BONUS_CAMEL: i32 = 7;
+const BONUS_CONSECUTIVE: i32 = 4;
+const FIRST_CHAR_MULTIPLIER: i32 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub score: i32,
+    /// Char indices (not byte offsets) of the matched characters, ascending.
+    pub positions: Vec<usize>,
+}
+
+impl Match {
+    /// The score scaled to `0.0..=1.0` against a pattern of `len` chars
+    /// that matched a whole word perfectly.
+    pub fn normalized(&self, len: usize) -> f64 {
+        let len = len.max(1) as i32;
+        let perfect =
+            len * (SCORE_MATCH + BONUS_BOUNDARY) + BONUS_BOUNDARY * (FIRST_CHAR_MULTIPLIER - 1);
+        (self.score as f64 / perfect as f64).clamp(0.0, 1.0)
+    }
+}
+
+/// Bonus for matching `cur` right after `prev` (`None` at the start).
+fn bonus(prev: Option<char>, cur: char) -> i32 {
+    let Some(prev) = prev else {
+        return BONUS_BOUNDARY;
+    };
+    if !prev.is_alphanumeric() && cur.is_alphanumeric() {
+        BONUS_BOUNDARY
+    } else if (prev.is_lowercase() && cur.is_uppercase())
+        || (prev.is_alphabetic() && cur.is_numeric())
+        || (prev.is_numeric() && cur.is_alphabetic())
+    {
+        BONUS_CAMEL
+    } else {
+        0
+    }
+}
+
+fn gap_cost(gap: usize) -> i32 {
+    if gap == 0 {
+        0
+    } else {
+        GAP_START + GAP_EXTENSION * (gap as i32 - 1)
+    }
+}
+
+/// Buffers `fuzzy_match` reuses from call to call on each thread, so that
+/// scoring every host on every key doesn't allocate a table per host.
+#[derive(Default)]
+struct Scratch {
+    pattern: Vec<char>,
+    original: Vec<char>,
+    text: Vec<char>,
+    /// For each char of `text`, the char of `original` it was folded from.
+    origin: Vec<usize>,
+    bonuses: Vec<i32>,
+    /// The `m` by `n` table of `place`, row after row.
+    best: Vec<Option<(i32, i32)>>,
+}
+
+thread_local! {
+    static SCRATCH: RefCell<Scratch> = RefCell::new(Scratch::default());
+}
+
+/// Finds the best placement of `pattern` as a case-insensitive subsequence
+/// of `text`, or `None` when it isn't one. An empty pattern matches anything.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<Match> {
+    if pattern.is_empty() {
+        return Some(Match {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+    // cheap rejection before anything is allocated or the quadratic part
+    let mut rest = text.chars().flat_map(fold_case);
+    if !pattern
+        .chars()
+        .flat_map(fold_case)
+        .all(|p| rest.any(|t| t == p))
+    {
+        return None;
+    }
+    SCRATCH.with(|scratch| place(pattern, text, &mut scratch.borrow_mut()))
+}
+
+/// `fuzzy_match` for a `pattern` known to be a subsequence of `text`,
+/// in the buffers of `scratch`.
+fn place(pattern: &str, text: &str, scratch: &mut Scratch) -> Option<Match> {
+    let Scratch {
+        pattern: chars,
+        original,
+        text: folded,
+        origin,
+        bonuses,
+        best,
+    } = scratch;
+    chars.clear();
+    chars.extend(pattern.chars().flat_map(fold_case));
+    original.clear();
+    original.extend(text.chars());
+    folded.clear();
+    origin.clear();
+    for (k, &c) in original.iter().enumerate() {
+        for c in fold_case(c) {
+            folded.push(c);
+            origin.push(k);
+        }
+    }
+    let (pattern, text) = (&chars[..], &folded[..]);
+    // `ß` folds to `ss`, and its second `s` starts nothing
+    bonuses.clear();
+    bonuses.extend((0..text.len()).map(|j| match origin[j] {
+        k if j > 0 && origin[j - 1] == k => 0,
+        k => bonus(k.checked_sub(1).map(|k| original[k]), original[k]),
+    }));
+    let (m, n) = (pattern.len(), text.len());
+    // best[i * n + j]: best score with pattern[..=i] placed and pattern[i]
+    // on text[j], plus the bonus of the char that started its consecutive run
+    best.clear();
+    best.resize(m * n, None);
+    for j in 0..n {
+        if text[j] == pattern[0] {
+            best[j] = Some((SCORE_MATCH + bonuses[j] * FIRST_CHAR_MULTIPLIER, bonuses[j]));
+        }
+    }
+    // a run keeps the bonus of its first char, so `prod` in `prod-db` beats
+    // the scattered boundaries of `p-r-o-d`
+    let consecutive = |(score, head): (i32, i32), j: usize| {
+        let bonus = head.max(bonuses[j]).max(BONUS_CONSECUTIVE);
+        (score + bonus, bonus)
+    };
+    for i in 1..m {
+        let (done, todo) = best.split_at_mut(i * n);
+        let (prev, row) = (&done[(i - 1) * n..], &mut todo[..n]);
+        // best predecessor at least one char back, gap cost already paid
+        let mut gapped: Option<i32> = None;
+        for j in 1..n {
+            if j >= 2 {
+                let from = prev[j - 2].map(|(s, _)| s - GAP_START);
+                gapped = match (gapped.map(|g| g - GAP_EXTENSION), from) {
+                    (Some(a), Some(b)) => Some(a.max(b)),
+                    (a, b) => a.or(b),
+                };
+            }
+            if text[j] != pattern[i] {
+                continue;
+            }
+            let run = prev[j - 1].map(|cell| consecutive(cell, j));
+            let jumped = gapped.map(|s| (s + bonuses[j], bonuses[j]));
+            row[j] = match (run, jumped) {
+                (Some(a), Some(b)) => Some(if b.0 > a.0 { b } else { a }),
+                (a, b) => a.or(b),
+            }
+            .map(|(s, head)| (s + SCORE_MATCH, head));
+        }
+    }
+
+    let (mut j, score) = best[(m - 1) * n..]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, cell)| cell.map(|(s, _)| (j, s)))
+        .max_by_key(|(j, s)| (*s, std::cmp::Reverse(*j)))?;
+    let mut positions = vec![j];
+    for i in (1..m).rev() {
+        let target = best[i * n + j].unwrap().0 - SCORE_MATCH;
+        // walk back to whichever predecessor produced this cell
+        j = (0..j)
+            .rev()
+            .find(|&k| {
+                best[(i - 1) * n + k].is_some_and(|cell| {
+                    if k + 1 == j {
+                        consecutive(cell, j).0 == target
+                    } else {
+                        cell.0 - gap_cost(j - k - 1) + bonuses[j] == target
+                    }
+                })
+            })
+            .unwrap();
+        positions.push(j);
+    }
+    let mut positions: Vec<usize> = positions.into_iter().rev().map(|j| origin[j]).collect();
+    positions.dedup();
+    Some(Match { score, positions })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn positions(pattern: &str, text: &str) -> Vec<usize> {
+        fuzzy_match(pattern, text).unwrap().positions
+    }
+
+    fn score(pattern: &str, text: &str) -> i32 {
+        fuzzy_match(pattern, text).map_or(i32::MIN, |m| m.score)
+    }
+
+    #[test]
+    fn abbreviations_match() {
+        assert_eq!(positions("wbprd", "web-prod"), vec![0, 2, 4, 5, 7]);
+        assert!(fuzzy_match("WBPRD", "web-prod").is_some());
+        assert!(fuzzy_match("wpb", "web-prod").is_none());
+        assert!(fuzzy_match("", "anything").is_some());
+    }
+
+    #[test]
+    fn prefers_word_boundaries() {
+        // `p` should land on the start of `prod`, not inside `api`
+        assert_eq!(positions("p", "api-prod"), vec![4]);
+        assert!(score("dp", "db-prod") > score("dp", "dumpster"));
+    }
+
+    #[test]
+    fn prefers_camel_case() {
+        assert_eq!(positions("wp", "webProxy"), vec![0, 3]);
+        assert!(score("wp", "webProxy") > score("wp", "webproxy"));
+    }
+
+    #[test]
+    fn prefers_consecutive_runs() {
+        assert!(score("prod", "prod-db") > score("prod", "p-r-o-d"));
+        assert_eq!(positions("db", "xdb-db"), vec![4, 5]);
+    }
+
+    #[test]
+    fn positions_are_chars_not_bytes() {
+        assert_eq!(positions("ab", "éa-b"), vec![1, 3]);
+    }
+
+    #[test]
+    fn reused_buffers_start_clean() {
+        let long = positions("wbprd", "a-much-longer-web-prod-name");
+        assert_eq!(positions("wp", "web-prod"), vec![0, 4]);
+        assert_eq!(positions("wbprd", "a-much-longer-web-prod-name"), long);
+    }
+
+    #[test]
+    fn positions_index_the_text_unfolded() {
+        assert_eq!(positions("strasse", "Straße-1"), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(positions("ss", "maße"), vec![2]);
+        assert_eq!(positions("ß", "gross"), vec![3, 4]);
+        assert_eq!(positions("ist", "İstanbul"), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn normalized_stays_in_range() {
+        let m = fuzzy_match("web", "web").unwrap();
+        assert!(m.normalized(3) > 0.9 && m.normalized(3) <= 1.0);
+        let m = fuzzy_match("wd", "w-------------------------d").unwrap();
+        assert!(m.normalized(2) >= 0.0);
+    }
+
+    proptest! {
+        #[test]
+        fn positions_spell_the_pattern(pattern in "[a-dA-D-]{0,5}", text in "[a-dA-D-]{0,20}") {
+            if let Some(m) = fuzzy_match(&pattern, &text) {
+                let text: Vec<char> = text.chars().collect();
+                let spelled: String = m.positions.iter().flat_map(|&j| fold_case(text[j])).collect();
+                prop_assert_eq!(spelled, pattern.to_lowercase());
+                prop_assert!(m.positions.windows(2).all(|w| w[0] < w[1]));
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Connection history: one `timestamp<TAB>alias` line per connection,
+//! appended every time gossh hands a host over to ssh.
+use crate::list::expand_tilde;
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A visit loses half its weight every week.
+const HALF_LIFE_SECS: f64 = 7.0 * 24.0 * 3600.0;
+/// Past this many lines the log is rewritten with the newest half.
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Debug, Default, Clone)]
+pub struct History {
+    visits: HashMap<String, Vec<u64>>,
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+impl History {
+    pub fn path() -> Option<PathBuf> {
+        let dir = match env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => {
+                env::var_os("HOME")?;
+                expand_tilde("~/.local/share")
+            }
+        };
+        Some(dir.join("gossh").join("history"))
+    }
+    /// Reads the log at `path`; a missing or unreadable log is an empty history.
+    pub fn load(path: &Path) -> History {
+        let mut history = History::default();
+        for line in fs::read_to_string(path).unwrap_or_default().lines() {
+            let Some((time, alias)) = line.split_once('\t') else {
+                continue;
+            };
+            if let Ok(time) = time.parse() {
+                history.add(alias, time);
+            }
+        }
+        history
+    }
+    pub fn add(&mut self, alias: &str, time: u64) {
+        self.visits.entry(alias.to_owned()).or_default().push(time);
+    }
+    /// Appends a connection to `alias` at `time` to the log at `path`.
+    pub fn record(path: &Path, alias: &str, time: u64) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{time}\t{alias}")?;
+        drop(file);
+
+        let text = fs::read_to_string(path)?;
+        let lines: Vec<&str> = text.lines().collect();
+        if lines.len() > MAX_ENTRIES {
+            let keep = &lines[lines.len() - MAX_ENTRIES / 2..];
+            let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+            fs::write(&tmp, keep.join("\n") + "\n")?;
+            fs::rename(tmp, path)?;
+        }
+        Ok(())
+    }
+    pub fn count(&self, alias: &str) -> usize {
+        self.visits.get(alias).map_or(0, Vec::len)
+    }
+    pub fn last(&self, alias: &str) -> Option<u64> {
+        self.visits.get(alias)?.iter().copied().max()
+    }
+    /// Frequency and recency in one number: every visit counts 1.0 when it
+    /// just happened and decays by half each week.
+    pub fn frecency(&self, alias: &str, now: u64) -> f64 {
+        self.visits.get(alias).map_or(0.0, |times| {
+            times
+                .iter()
+                .map(|&t| {
+                    let age = now.saturating_sub(t) as f64;
+                    (-std::f64::consts::LN_2 * age / HALF_LIFE_SECS).exp()
+                })
+                .sum()
+        })
+    }
+    /// `frecency` squashed into `0.0..1.0`, to be mixed with match scores.
+    pub fn boost(&self, alias: &str, now: u64) -> f64 {
+        let frecency = self.frecency(alias, now);
+        frecency / (frecency + 1.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DAY: u64 = 24 * 3600;
+
+    #[test]
+    fn records_and_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gossh").join("history");
+        History::record(&path, "web1", 100).unwrap();
+        History::record(&path, "web1", 200).unwrap();
+        History::record(&path, "db", 150).unwrap();
+
+        let history = History::load(&path);
+        assert_eq!(history.count("web1"), 2);
+        assert_eq!(history.last("web1"), Some(200));
+        assert_eq!(history.count("mail"), 0);
+        assert_eq!(History::load(&dir.path().join("missing")).count("web1"), 0);
+    }
+
+    #[test]
+    fn frequent_and_recent_rank_higher() {
+        let now = 100 * DAY;
+        let mut history = History::default();
+        history.add("often", now - DAY);
+        history.add("often", now - 2 * DAY);
+        history.add("once", now - DAY);
+        history.add("stale", now - 60 * DAY);
+        history.add("stale", now - 61 * DAY);
+
+        assert!(history.frecency("often", now) > history.frecency("once", now));
+        assert!(history.frecency("once", now) > history.frecency("stale", now));
+        assert_eq!(history.frecency("never", now), 0.0);
+        assert!(history.boost("often", now) < 1.0);
+    }
+}
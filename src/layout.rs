@@ -0,0 +1,279 @@
+//! Keyboard layouts the search box folds back to QWERTY, so a query typed
+//! with the wrong layout active still finds the host: `صثذ` is `web`.
+use std::collections::HashMap;
+
+/// Layouts folded when the config doesn't pick any. Dvorak and Colemak
+/// remap plain letters, so they are only used when asked for.
+pub const DEFAULT: &[&str] = &["persian", "arabic", "russian", "hebrew"];
+
+/// What each key types in a layout (`from`) and on QWERTY (`to`), char by
+/// char. Only keys that type something QWERTY can't are listed for the
+/// non-Latin layouts, so `.` and digits in addresses are left alone.
+const BUILTIN: &[(&str, &str, &str)] = &[
+    (
+        // ISIRI 9147. Shift+D and Shift+Z type `ي` and `ك`, which are
+        // folded as the `ی` and `ک` older keyboards type them in place of.
+        "persian",
+        concat!(
+            "\u{200d}۱۲۳۴۵۶۷۸۹۰",
+            "ضصثقفغعهخحجچ",
+            "شسیبلاتنمکگ",
+            "ظطزرذدپو",
+            "÷٬٫﷼٪×،ـ",
+            "\u{652}\u{64c}\u{64d}\u{64b}\u{64f}\u{650}\u{64e}\u{651}",
+            "ؤئإأآة»«",
+            "\u{653}ژ\u{670}\u{200c}\u{654}ء؟",
+        ),
+        concat!(
+            "`1234567890",
+            "qwertyuiop[]",
+            "asdfghjkl;'",
+            "zxcvbnm,",
+            "~@#$%^&_",
+            "QWERTYUI",
+            "ASFGHJKL",
+            "XCVBNM?",
+        ),
+    ),
+    (
+        // Arabic (101). The `لا`, `لأ`, `لإ` and `لآ` keys type two chars
+        // and are left to the chars they're made of.
+        "arabic",
+        concat!(
+            "ذ٠١٢٣٤٥٦٧٨٩",
+            "ضصثقفغعهخحجد",
+            "شسيبلاتنمكط",
+            "ئءؤرىةوزظ",
+            "\u{651}",
+            "\u{64e}\u{64b}\u{64f}\u{64c}إ‘÷×؛",
+            "\u{650}\u{64d}أـ،",
+            "\u{652}آ’؟",
+        ),
+        concat!(
+            "`0123456789",
+            "qwertyuiop[]",
+            "asdfghjkl;'",
+            "zxcvnm,./",
+            "~",
+            "QWERYUIOP",
+            "ASHJK",
+            "XNM?",
+        ),
+    ),
+    (
+        "russian",
+        "ёйцукенгшщзхъфывапролджэячсмитьбю",
+        "`qwertyuiop[]asdfghjkl;'zxcvbnm,.",
+    ),
+    (
+        "hebrew",
+        "קראטוןםפשדגכעיחלךףזסבהנמצתץ",
+        "ertyuiopasdfghjkl;zxcvbnm,.",
+    ),
+    (
+        "dvorak",
+        "[]',.pyfgcrl/=aoeuidhtns-;qjkxbmwvz",
+        "-=qwertyuiop[]asdfghjkl;'zxcvbnm,./",
+    ),
+    (
+        "colemak",
+        "qwfpgjluy;arstdhneiozxcvbkm",
+        "qwertyuiopasdfghjkl;zxcvbnm",
+    ),
+];
+
+/// Chars some keyboards type in place of one of a layout's own: the Arabic
+/// `ي`, `ى` and `ك` and the Arabic-Indic digits on older Persian keyboards.
+/// They fold like the char they stand in for.
+const VARIANTS: &[(&str, &str, &str)] = &[("persian", "يىك٠١٢٣٤٥٦٧٨٩", "ییک۰۱۲۳۴۵۶۷۸۹")];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    pub name: String,
+    map: HashMap<char, char>,
+}
+
+impl Layout {
+    /// A layout typing the chars of `from` where QWERTY types those of
+    /// `to`. Letters with a case get their shifted pair too.
+    pub fn new(name: &str, from: &str, to: &str) -> Result<Layout, String> {
+        let (from_len, to_len) = (from.chars().count(), to.chars().count());
+        if from_len != to_len {
+            return Err(format!("layout `{name}` maps {from_len} chars to {to_len}"));
+        }
+        let mut map = HashMap::new();
+        for (f, t) in from.chars().zip(to.chars()) {
+            map.insert(f, t);
+            if f.is_lowercase() && t.is_lowercase() {
+                map.entry(upper(f)).or_insert(upper(t));
+            }
+        }
+        Ok(Layout {
+            name: name.to_owned(),
+            map,
+        })
+    }
+    pub fn translate(&self, c: char) -> Option<char> {
+        self.map.get(&c).copied()
+    }
+    pub fn fold(&self, input: &str) -> String {
+        input
+            .chars()
+            .map(|c| self.translate(c).unwrap_or(c))
+            .collect()
+    }
+}
+
+fn upper(c: char) -> char {
+    c.to_uppercase().next().unwrap_or(c)
+}
+
+pub fn builtin() -> Vec<Layout> {
+    let mut layouts: Vec<Layout> = BUILTIN
+        .iter()
+        .map(|(name, from, to)| Layout::new(name, from, to).expect("built-in layouts are valid"))
+        .collect();
+    for (name, variants, standard) in VARIANTS {
+        let layout = layouts.iter_mut().find(|l| l.name == *name).unwrap();
+        for (variant, standard) in variants.chars().zip(standard.chars()) {
+            let key = layout.map[&standard];
+            layout.map.insert(variant, key);
+        }
+    }
+    layouts
+}
+
+/// The layouts the search box tries folding a query back from.
+#[derive(Debug, Clone, Default)]
+pub struct Layouts {
+    layouts: Vec<Layout>,
+}
+
+impl Layouts {
+    /// Picks the layouts called `names` from `custom` and the built-ins; a
+    /// custom layout replaces a built-in one of the same name.
+    pub fn select(names: &[&str], custom: Vec<Layout>) -> Result<Layouts, String> {
+        let mut known = custom;
+        for layout in builtin() {
+            if !known.iter().any(|l| l.name == layout.name) {
+                known.push(layout);
+            }
+        }
+        let layouts = names
+            .iter()
+            .map(|&name| match known.iter().find(|l| l.name == name) {
+                Some(layout) => Ok(layout.clone()),
+                None => {
+                    let names: Vec<&str> = known.iter().map(|l| l.name.as_str()).collect();
+                    Err(format!(
+                        "unknown layout {name:?}, expected one of: {}",
+                        names.join(", ")
+                    ))
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Layouts { layouts })
+    }
+    /// `input` folded back from each layout that changes it, as the
+    /// layout's name and the text; a text two layouts agree on comes once.
+    pub fn translations(&self, input: &str) -> Vec<(&str, String)> {
+        let mut found: Vec<(&str, String)> = Vec::new();
+        for layout in &self.layouts {
+            let text = layout.fold(input);
+            if text != input && !found.iter().any(|(_, t)| *t == text) {
+                found.push((&layout.name, text));
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fold(name: &str, input: &str) -> String {
+        builtin()
+            .into_iter()
+            .find(|l| l.name == name)
+            .unwrap()
+            .fold(input)
+    }
+
+    fn unique(text: &str) -> bool {
+        let mut chars: Vec<char> = text.chars().collect();
+        chars.sort_unstable();
+        chars.dedup();
+        chars.len() == text.chars().count()
+    }
+
+    #[test]
+    fn builtins_are_consistent() {
+        for (name, from, to) in BUILTIN {
+            assert_eq!(from.chars().count(), to.chars().count(), "{name}");
+            // one char per key and one key per char, or folding is ambiguous
+            assert!(unique(from), "{name} types a char on two keys");
+            assert!(unique(to), "{name} has two chars on one key");
+            assert!(to.is_ascii(), "{name}");
+            if !["dvorak", "colemak"].contains(name) {
+                assert!(!from.chars().any(|c| c.is_ascii()), "{name} remaps ASCII");
+            }
+        }
+        for (name, variants, standard) in VARIANTS {
+            let (_, from, _) = BUILTIN.iter().find(|(n, ..)| n == name).unwrap();
+            assert_eq!(variants.chars().count(), standard.chars().count(), "{name}");
+            assert!(unique(variants), "{name}");
+            for (variant, standard) in variants.chars().zip(standard.chars()) {
+                assert!(from.contains(standard), "{name}: {standard}");
+                assert!(!from.contains(variant), "{name}: {variant}");
+            }
+        }
+    }
+
+    #[test]
+    fn persian_shift_layer_and_variants() {
+        assert_eq!(fold("persian", "آژ\u{200c}؟"), "HCB?");
+        // `و` and `،` no longer both fold to `,`
+        assert_eq!(fold("persian", "و،"), ",&");
+        assert_eq!(fold("persian", "يك٠۱"), "d;01");
+        assert_eq!(fold("persian", "\u{651}ـ"), "I_");
+        assert_eq!(fold("arabic", "أ\u{651}؛"), "H~P");
+    }
+
+    #[test]
+    fn folds_each_script() {
+        assert_eq!(fold("persian", "صثذ-حقخی۰۱"), "web-prod01");
+        assert_eq!(fold("arabic", "صثلاقخي"), "weghrod");
+        assert_eq!(fold("russian", "цуи-зкщв01"), "web-prod01");
+        assert_eq!(fold("russian", "ЦУИ"), "WEB");
+        assert_eq!(fold("hebrew", "'קנ-פרםג01"), "'eb-prod01");
+        assert_eq!(fold("dvorak", ",.x"), "web");
+        assert_eq!(fold("colemak", "wfb"), "web");
+        // addresses and ASCII pass through the non-Latin layouts
+        assert_eq!(fold("persian", "10.0.0.1 web"), "10.0.0.1 web");
+    }
+
+    #[test]
+    fn translations_skip_layouts_that_change_nothing() {
+        let layouts = Layouts::select(DEFAULT, Vec::new()).unwrap();
+        assert!(layouts.translations("web").is_empty());
+        // `د` is n in Persian and ] in Arabic
+        let found = layouts.translations("د");
+        assert_eq!(
+            found,
+            [("persian", "n".to_owned()), ("arabic", "]".to_owned())]
+        );
+        // where they agree the text comes once
+        assert_eq!(layouts.translations("صث"), [("persian", "we".to_owned())]);
+    }
+
+    #[test]
+    fn custom_layouts() {
+        let custom = Layout::new("persian", "ص", "x").unwrap();
+        let layouts = Layouts::select(&["persian"], vec![custom]).unwrap();
+        assert_eq!(layouts.translations("صث"), [("persian", "xث".to_owned())]);
+        assert!(Layout::new("broken", "ab", "c").is_err());
+        let error = Layouts::select(&["klingon"], Vec::new()).unwrap_err();
+        assert!(error.contains("persian, arabic"), "{error}");
+    }
+}
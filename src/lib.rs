@@ -0,0 +1,21 @@
+pub mod batch;
+pub mod bindings;
+pub mod cache;
+pub mod cli;
+pub mod config;
+pub mod finglish;
+pub mod fuzzy;
+pub mod history;
+pub mod layout;
+pub mod list;
+pub mod navigation;
+pub mod normalize;
+pub mod preview;
+pub mod query;
+pub mod rank;
+pub mod scorer;
+pub mod search;
+pub mod segment;
+pub mod ssh_config;
+pub mod table;
+pub mod theme;
@@ -1,16 +1,11 @@
+use crate::cache::{self, Source};
+use crate::ssh_config::{Body, Config, Eol, Line};
 use nom::IResult;
-use nom::Parser;
-use nom::branch::alt;
-use nom::bytes::complete::take_until;
-use nom::character::complete::{multispace0, newline};
-use nom::combinator::rest;
-use nom::multi::separated_list0;
-use nom::sequence::delimited;
+use serde::{Deserialize, Serialize};
 use std::default::Default;
 use std::env;
-use std::fs::File;
-use std::io::BufReader;
-use std::io::prelude::*;
+use std::fs;
+use std::io::{self, Read};
 use std::path::Path;
 use std::path::PathBuf;
 use std::process;
@@ -18,14 +13,26 @@ use users::{get_current_uid, get_user_by_uid};
 // Import (via `use`) the `fmt` module to make it available.
 use std::fmt;
 pub struct Server {}
-#[derive(Default, Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Default, Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct List {
-    pub score: f64,
     pub hostname: String,
     pub alias: String,
     pub display_name: String,
+    /// Further non-pattern names on the `Host` line.
+    pub aliases: Vec<String>,
+    pub user: String,
+    pub port: Option<u16>,
+    /// From `Tag` lines and `# tags: a, b` comments inside the block.
+    pub tags: Vec<String>,
+    /// From a `# description: ...` comment inside the block.
+    pub description: String,
+    /// The file and 1-based line of the `Host` line, where known.
+    pub source: Option<(PathBuf, usize)>,
 }
 
+/// Where each line of a config with its includes spliced in came from.
+type Origins = Vec<Option<(PathBuf, usize)>>;
+
 impl fmt::Display for List {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // Write strictly the first element into the supplied output
@@ -45,81 +52,301 @@ fn is_valid_home(username: &str, home: &Path) -> bool {
         .unwrap_or(false);
 
     // if you want to enable root user, uncomment the two code below
-    let is_root = home == Path::new("/root");
-     //is_home_match || is_root
+    //let is_root = home == Path::new("/root");
+    //is_home_match || is_root
 
     is_home_match
-
 }
 
-fn expand_tilde(path: &str) -> PathBuf {
-    if let Some(stripped) = path.strip_prefix("~/") {
-        if let Some(home) = env::var_os("HOME") {
-            let current_uid = get_current_uid();
-            let user = get_user_by_uid(current_uid)
-                .ok_or("Failed to get current user")
-                .expect("Failed to get user");
-            let username = user.name().to_string_lossy();
-            if is_valid_home(&username, Path::new(&home)) {
-                return PathBuf::from(home).join(stripped);
-            } else {
-                eprintln!("Error: Permission denied");
-                eprintln!("{:#?}, {:#?}", username, Path::new(&home));
-                process::exit(1); // exit code 1 is conventional for general errors
-            }
+pub fn expand_tilde(path: &str) -> PathBuf {
+    if let Some(stripped) = path.strip_prefix("~/")
+        && let Some(home) = env::var_os("HOME")
+    {
+        let current_uid = get_current_uid();
+        let user = get_user_by_uid(current_uid)
+            .ok_or("Failed to get current user")
+            .expect("Failed to get user");
+        let username = user.name().to_string_lossy();
+        if is_valid_home(&username, Path::new(&home)) {
+            return PathBuf::from(home).join(stripped);
+        } else {
+            eprintln!("Error: Permission denied");
+            eprintln!("{:#?}, {:#?}", username, Path::new(&home));
+            process::exit(1); // exit code 1 is conventional for general errors
         }
     }
     PathBuf::from(path)
 }
 
+/// Matches `name` against an ssh-style pattern where `*` matches any run of
+/// characters and `?` matches exactly one.
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn split_tags(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|t| !t.is_empty())
+        .map(str::to_owned)
+}
+
+/// Includes ssh follows inside includes, as `READCONF_MAX_DEPTH` in OpenSSH.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Adds `path` to the fingerprints of a host list, once.
+fn record(sources: &mut Vec<Source>, path: &Path) {
+    if !sources.iter().any(|s| s.path == path) {
+        sources.push(Source::stat(path));
+    }
+}
+
 impl Server {
-    pub fn get_list() -> String {
-        let mut input = String::new();
-        let config_file = expand_tilde("~/.ssh/config");
-        let file = File::open(config_file).unwrap();
-        let mut buf_reader = BufReader::new(file);
-        buf_reader.read_to_string(&mut input).unwrap();
+    pub fn config_path() -> PathBuf {
+        expand_tilde("~/.ssh/config")
+    }
+    /// Loads the host list for the user's ssh config, going through the
+    /// on-disk cache when it is still fresh.
+    pub fn load() -> Vec<List> {
+        Server::load_from(&Server::config_path(), cache::cache_path().as_deref())
+    }
+    pub fn load_from(config_file: &Path, cache_file: Option<&Path>) -> Vec<List> {
+        if let Some(hosts) = cache_file.and_then(|cache_file| cache::load(cache_file, config_file))
+        {
+            return hosts;
+        }
+        let mut sources = Vec::new();
+        let mut origins = Vec::new();
+        let input = Server::read_config(config_file, &mut sources, &mut origins);
+        let hosts = Server::hosts(&input, &origins);
+        if let Some(cache_file) = cache_file {
+            // a cache we can't write is only a slower next start
+            let _ = cache::store(cache_file, config_file, &sources, &hosts);
+        }
+        hosts
+    }
+    pub fn from_path(config_file: &Path) -> Vec<List> {
+        Server::load_from(config_file, None)
+    }
+    /// Builds the host list from an already opened config; relative
+    /// `Include`s are resolved against `include_base`.
+    pub fn from_reader<R: Read>(mut reader: R, include_base: &Path) -> io::Result<Vec<List>> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let mut origins = Vec::new();
+        let input = Server::splice_includes(
+            &text,
+            None,
+            include_base,
+            &mut Vec::new(),
+            &mut Vec::new(),
+            &mut origins,
+        );
+        Ok(Server::hosts(&input, &origins))
+    }
+    /// Reads `path` and splices the files named by its `Include` lines in
+    /// place, recording every file and globbed directory that was consulted
+    /// and, in `origins`, where each line of the result came from.
+    pub fn read_config(path: &Path, sources: &mut Vec<Source>, origins: &mut Origins) -> String {
+        Server::read_nested(path, &mut Vec::new(), sources, origins)
+    }
+    /// `read_config` for a file included through the files in `stack`. A
+    /// file including itself, directly or not, is left out there, as is
+    /// anything nested deeper than ssh reads; a file included twice
+    /// elsewhere is spliced in both times, as ssh applies it both times.
+    fn read_nested(
+        path: &Path,
+        stack: &mut Vec<PathBuf>,
+        sources: &mut Vec<Source>,
+        origins: &mut Origins,
+    ) -> String {
+        if stack.len() > MAX_INCLUDE_DEPTH || stack.iter().any(|p| p == path) {
+            return String::new();
+        }
+        record(sources, path);
+        let Ok(text) = fs::read_to_string(path) else {
+            return String::new();
+        };
+        let base = path.parent().unwrap_or(Path::new("."));
+        stack.push(path.to_path_buf());
+        let input = Server::splice_includes(&text, Some(path), base, stack, sources, origins);
+        stack.pop();
         input
     }
+    fn splice_includes(
+        text: &str,
+        path: Option<&Path>,
+        base: &Path,
+        stack: &mut Vec<PathBuf>,
+        sources: &mut Vec<Source>,
+        origins: &mut Origins,
+    ) -> String {
+        let mut input = String::with_capacity(text.len());
+        for (number, line) in Config::parse(text).lines.into_iter().enumerate() {
+            let origin = path.map(|path| (path.to_path_buf(), number + 1));
+            match line.key_value() {
+                Some((key, value)) if key.eq_ignore_ascii_case("include") => {
+                    for pattern in value.split_whitespace() {
+                        for file in Server::resolve_include(base, pattern, sources) {
+                            input.push_str(&Server::read_nested(&file, stack, sources, origins));
+                            input.push('\n');
+                            origins.push(origin.clone());
+                        }
+                    }
+                }
+                _ => {
+                    input.push_str(&line.to_string());
+                    origins.push(origin);
+                }
+            }
+        }
+        if !input.ends_with('\n') {
+            input.push('\n');
+        }
+        input
+    }
+    fn resolve_include(base: &Path, pattern: &str, sources: &mut Vec<Source>) -> Vec<PathBuf> {
+        let path = match pattern.strip_prefix("~/") {
+            Some(_) => expand_tilde(pattern),
+            None => base.join(pattern),
+        };
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            return vec![path];
+        };
+        if !name.contains(['*', '?']) {
+            return vec![path];
+        }
+        let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        // files appearing in or leaving the directory change its mtime
+        record(sources, &dir);
+        let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|e| {
+                e.file_name()
+                    .to_str()
+                    .is_some_and(|f| wildcard_match(name, f))
+            })
+            .map(|e| e.path())
+            .collect();
+        files.sort();
+        files
+    }
+    pub(crate) fn starts_block(line: &str) -> bool {
+        Line::parse(line, Eol::None)
+            .key_value()
+            .is_some_and(|(key, _)| {
+                key.eq_ignore_ascii_case("host") || key.eq_ignore_ascii_case("match")
+            })
+    }
+    /// Cuts the config into chunks that each start at a `Host` or `Match` line
+    /// (the first chunk holds whatever precedes them).
     pub fn parse_list(input: &str) -> IResult<&str, Vec<&str>> {
-        delimited(
-            multispace0,
-            separated_list0(newline, alt((take_until("\nHost "), rest))),
-            multispace0,
-        )
-        .parse(input)
+        let mut blocks = Vec::new();
+        let (mut start, mut offset) = (0, 0);
+        for line in input.split_inclusive('\n') {
+            if offset > start && Server::starts_block(line) {
+                blocks.push(&input[start..offset]);
+                start = offset;
+            }
+            offset += line.len();
+        }
+        if start < input.len() {
+            blocks.push(&input[start..]);
+        }
+        Ok(("", blocks))
+    }
+    /// Picks up `# tags: ...` and `# description: ...` comments.
+    fn read_annotation(server: &mut List, comment: &str) {
+        let Some((key, value)) = comment.trim_start_matches('#').split_once(':') else {
+            return;
+        };
+        match key.trim().to_ascii_lowercase().as_str() {
+            "tags" => server.tags.extend(split_tags(value)),
+            "description" => server.description = value.trim().to_owned(),
+            _ => {}
+        }
+    }
+    /// The hosts of a spliced config, each with the origin of its first line.
+    fn hosts(input: &str, origins: &Origins) -> Vec<List> {
+        let (_, blocks) = Server::parse_list(input).unwrap();
+        let mut hosts = Vec::new();
+        let mut line = 0;
+        for block in blocks {
+            if let Some(mut host) = Server::parse_host(block) {
+                host.source = origins.get(line).cloned().flatten();
+                hosts.push(host);
+            }
+            line += block.split_inclusive('\n').count();
+        }
+        hosts
     }
     pub fn hash_list(list: Vec<&str>) -> Vec<List> {
-        let mut servers = Vec::new();
-        for item in list {
-            let mut server = List::default();
-            for i in item.split("\n").map(|f| f.trim()) {
-                let mut i = i.split(" ");
-                let _1 = i.next().unwrap_or("");
-                let _2 = i.next().unwrap_or("");
-                if _1.starts_with("HostName") {
-                    server.hostname = _2.to_owned();
-                    if server
-                        .hostname
-                        .chars()
-                        .filter(|c| *c != '.')
-                        .collect::<Vec<_>>()
-                        .iter()
-                        .all(|c| c.is_ascii_digit())
-                    {
-                        server.display_name = server.hostname.clone();
-                    } else {
-                        server.display_name = server.hostname.split(".").next().unwrap().to_owned();
-                    }
-                } else if _1.starts_with("Host") {
-                    server.alias = _2.to_owned();
-                }
+        list.into_iter().filter_map(Server::parse_host).collect()
+    }
+    /// The host a `Host` block defines, if it names one with a hostname.
+    fn parse_host(item: &str) -> Option<List> {
+        let mut server = List::default();
+        for line in Config::parse(item).lines {
+            if let Body::Comment(comment) = &line.body {
+                Server::read_annotation(&mut server, comment);
+                continue;
             }
-            if !server.hostname.is_empty() && !server.alias.is_empty() {
-                servers.push(server);
+            let Some((key, value)) = line.key_value() else {
+                continue;
+            };
+            let value = value.trim_matches('"');
+            if key.eq_ignore_ascii_case("hostname") {
+                server.hostname = value.to_owned();
+                if server
+                    .hostname
+                    .chars()
+                    .filter(|c| *c != '.')
+                    .collect::<Vec<_>>()
+                    .iter()
+                    .all(|c| c.is_ascii_digit())
+                {
+                    server.display_name = server.hostname.clone();
+                } else {
+                    server.display_name = server.hostname.split(".").next().unwrap().to_owned();
+                }
+            } else if key.eq_ignore_ascii_case("host") {
+                // patterns like `*` or `!bad` are defaults, not hosts you can pick
+                let mut names = value
+                    .split_whitespace()
+                    .filter(|p| !p.contains(['*', '?', '!']))
+                    .map(str::to_owned);
+                server.alias = names.next().unwrap_or_default();
+                server.aliases = names.collect();
+            } else if key.eq_ignore_ascii_case("user") {
+                server.user = value.to_owned();
+            } else if key.eq_ignore_ascii_case("port") {
+                server.port = value.parse().ok();
+            } else if key.eq_ignore_ascii_case("tag") {
+                server.tags.extend(split_tags(value));
             }
         }
-        servers
+        (!server.hostname.is_empty() && !server.alias.is_empty()).then_some(server)
     }
 }
 
@@ -127,15 +354,191 @@ impl Server {
 mod test {
     use super::*;
 
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+    }
+
+    fn render(hosts: &[List]) -> String {
+        hosts
+            .iter()
+            .map(|h| {
+                format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                    h.alias,
+                    h.aliases.join(","),
+                    h.hostname,
+                    h.display_name,
+                    h.user,
+                    h.port.map(|p| p.to_string()).unwrap_or_default(),
+                    h.tags.join(","),
+                    h.description
+                )
+            })
+            .collect()
+    }
+
+    /// Compares against `<name>.golden`; run with `GOSSH_BLESS=1` to rewrite it.
+    fn check_golden(name: &str) {
+        let dir = fixtures();
+        let actual = render(&Server::from_path(&dir.join(format!("{name}.config"))));
+        let golden = dir.join(format!("{name}.golden"));
+        if env::var_os("GOSSH_BLESS").is_some() {
+            fs::write(&golden, &actual).unwrap();
+        }
+        let expected = fs::read_to_string(&golden).unwrap();
+        assert_eq!(
+            actual, expected,
+            "fixture {name} differs from its golden file"
+        );
+    }
+
+    #[test]
+    fn basic() {
+        check_golden("basic");
+    }
+    #[test]
+    fn includes() {
+        check_golden("include");
+    }
+    #[test]
+    fn wildcards() {
+        check_golden("wildcards");
+    }
+    #[test]
+    fn tabs() {
+        check_golden("tabs");
+    }
+    #[test]
+    fn equals_sign() {
+        check_golden("equals");
+    }
+    #[test]
+    fn comments() {
+        check_golden("comments");
+    }
+    #[test]
+    fn crlf() {
+        check_golden("crlf");
+    }
+    #[test]
+    fn annotations() {
+        check_golden("annotations");
+    }
+
+    #[test]
+    fn reader_matches_path() {
+        let dir = fixtures();
+        let file = fs::File::open(dir.join("include.config")).unwrap();
+        // the same hosts, though only the included ones know their file
+        assert_eq!(
+            render(&Server::from_reader(file, &dir).unwrap()),
+            render(&Server::from_path(&dir.join("include.config")))
+        );
+    }
+
+    #[test]
+    fn repeated_includes_apply_each_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config");
+        fs::write(
+            &config,
+            "Host web\n    HostName web.lan\n    Include common\n\
+             Host db\n    HostName db.lan\n    Include common\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("common"), "    User deploy\n").unwrap();
+        let mut sources = Vec::new();
+        let input = Server::read_config(&config, &mut sources, &mut Vec::new());
+        assert_eq!(input.matches("User deploy").count(), 2);
+        // fingerprinted once all the same
+        assert_eq!(sources.len(), 2);
+        let users: Vec<String> = Server::from_path(&config)
+            .into_iter()
+            .map(|host| host.user)
+            .collect();
+        assert_eq!(users, ["deploy", "deploy"]);
+    }
+
+    #[test]
+    fn include_cycles_end() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config");
+        fs::write(&config, "Include a\n").unwrap();
+        fs::write(
+            dir.path().join("a"),
+            "Host a\n    HostName a.lan\nInclude b\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("b"), "Include a\nInclude config\n").unwrap();
+        let aliases: Vec<String> = Server::from_path(&config)
+            .into_iter()
+            .map(|host| host.alias)
+            .collect();
+        assert_eq!(aliases, ["a"]);
+
+        // a chain deeper than ssh follows is cut off where ssh stops
+        for depth in 0..20 {
+            let text = format!(
+                "Host h{depth}\n    HostName h{depth}.lan\nInclude {}\n",
+                depth + 1
+            );
+            fs::write(dir.path().join(depth.to_string()), text).unwrap();
+        }
+        let hosts = Server::from_path(&dir.path().join("0"));
+        assert_eq!(hosts.len(), MAX_INCLUDE_DEPTH + 1);
+    }
+
     #[test]
-    fn print_debug() {
-        let config_file: String = Server::get_list();
-        println!("{:#?}", Server::parse_list(&config_file));
+    fn records_where_hosts_are_defined() {
+        let dir = fixtures();
+        let config = dir.join("include.config");
+        let sources: Vec<(String, usize)> = Server::from_path(&config)
+            .into_iter()
+            .map(|host| {
+                let (path, line) = host.source.unwrap();
+                let path = path.strip_prefix(&dir).unwrap().display().to_string();
+                (path, line)
+            })
+            .collect();
+        let mut expected: Vec<(String, usize)> = Vec::new();
+        for file in Server::resolve_include(&dir, "include.d/*.conf", &mut Vec::new())
+            .into_iter()
+            .chain([dir.join("include.d/extra"), config.clone()])
+        {
+            let text = fs::read_to_string(&file).unwrap();
+            let path = file.strip_prefix(&dir).unwrap().display().to_string();
+            expected.extend(
+                text.lines()
+                    .enumerate()
+                    .filter(|(_, line)| line.starts_with("Host "))
+                    .map(|(i, _)| (path.clone(), i + 1)),
+            );
+        }
+        assert_eq!(sources, expected);
+        // a config read from elsewhere has no file to point to
+        let file = fs::File::open(&config).unwrap();
+        let hosts = Server::from_reader(file, &dir).unwrap();
+        assert_eq!(hosts.last().unwrap().source, None);
     }
+
+    #[test]
+    fn line_forms() {
+        for line in [
+            "HostName web",
+            "HostName=web",
+            "  HostName = web \t",
+            "\tHostName\tweb",
+        ] {
+            let line = Line::parse(line, Eol::Lf);
+            assert_eq!(line.key_value(), Some(("HostName", "web")));
+        }
+    }
+
     #[test]
-    fn print_list_debug() {
-        let config_file: String = Server::get_list();
-        let (_, list) = Server::parse_list(&config_file).unwrap();
-        println!("{:#?}", Server::hash_list(list));
+    fn wildcard_patterns() {
+        assert!(wildcard_match("*.conf", "a.conf"));
+        assert!(wildcard_match("web-?", "web-1"));
+        assert!(!wildcard_match("web-?", "web-10"));
+        assert!(wildcard_match("*", ""));
     }
 }
@@ -1,40 +1,120 @@
-mod list;
-mod tools;
-use signal_hook::consts::signal::{SIGHUP, SIGINT, SIGQUIT, SIGTERM};
-use signal_hook::iterator::Signals;
-use crossterm::event::MouseEventKind;
+use crossterm::event::{MouseButton, MouseEventKind};
 use crossterm::{
     cursor::MoveTo,
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+    },
     execute,
     terminal::{
         Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode,
         enable_raw_mode,
     },
 };
-use list::Server;
-use ratatui::widgets::ListState;
+use gossh::batch::{self, Batch, Choice};
+use gossh::bindings::{Action, Bindings, Mode};
+use gossh::cli::{self, Args};
+use gossh::config::Config;
+use gossh::history::{self, History};
+use gossh::layout::Layouts;
+use gossh::list::{self, Server};
+use gossh::navigation::Navigation;
+use gossh::preview;
+use gossh::query::Query;
+use gossh::rank::Field;
+use gossh::scorer;
+use gossh::search::{self, Hit, Search};
+use gossh::table::{self, Column};
+use gossh::theme::{Depth, Theme};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{List, ListState, Paragraph, TableState};
+use signal_hook::consts::signal::{SIGHUP, SIGINT, SIGQUIT, SIGTERM};
+use signal_hook::iterator::Signals;
 
 use ratatui::{
     Terminal,
     backend::{Backend, CrosstermBackend},
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Style},
-    widgets::{Block, Borders, List, ListItem},
+    layout::{Constraint, Direction, Layout, Position, Rect, Size},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, Row, Table},
 };
+use std::collections::BTreeSet;
 use std::env;
 use std::os::unix::process::CommandExt;
 use std::time::{Duration, Instant};
-use std::{
-    io,
-    process::Command,
-};
-use tui_textarea::TextArea;
+use std::{io, process::Command};
+use tui_scrollview::{ScrollView, ScrollViewState, ScrollbarVisibility};
+use tui_textarea::{CursorMove, TextArea};
 
 fn main() -> io::Result<()> {
-    let arg = env::args().nth(1); // 0 is the program name, so 1 is the first real argument
+    let args = Args::parse(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("gossh: {e}\n\n{}", cli::USAGE);
+        std::process::exit(2);
+    });
+    if args.help {
+        print!("{}", cli::USAGE);
+        return Ok(());
+    }
+    let config = match Config::path() {
+        Some(path) => Config::load(&path).unwrap_or_else(|e| {
+            eprintln!("gossh: {}: {e}", path.display());
+            std::process::exit(1);
+        }),
+        None => Config::default(),
+    };
+    // the flag wins over the config file, which wins over the default
+    let scorer = match args.scorer.as_deref().or(config.scorer.as_deref()) {
+        Some(name) => scorer::by_name(name).unwrap_or_else(|| {
+            eprintln!(
+                "gossh: unknown scorer {name:?}, expected one of: {}",
+                scorer::names().join(", ")
+            );
+            std::process::exit(1);
+        }),
+        None => scorer::SCORERS[0],
+    };
+    let settings = Settings::new(&config).unwrap_or_else(|e| {
+        eprintln!("gossh: {e}");
+        std::process::exit(1);
+    });
+    let list: Vec<list::List> = Server::load();
+    let history = History::path()
+        .map(|path| History::load(&path))
+        .unwrap_or_default();
+    let mut search = Search::new(&list, scorer, &history, history::now());
+
+    // decided before raw mode, so scripts never see the picker flash by
+    if args.select_1 || args.exit_0 {
+        let query = args.query.as_deref().unwrap_or_default();
+        let hits = match search.run_typed(query, &settings.layouts) {
+            Ok(found) => found.hits,
+            Err(error) => {
+                eprintln!("gossh: {error}");
+                std::process::exit(2);
+            }
+        };
+        if args.exit_0 && hits.is_empty() {
+            let near: Vec<&str> = search
+                .suggest(query)
+                .iter()
+                .map(|hit| list[hit.index].alias.as_str())
+                .collect();
+            match near.as_slice() {
+                [] => eprintln!("gossh: no host matches {query:?}"),
+                near => eprintln!(
+                    "gossh: no host matches {query:?}, did you mean: {}?",
+                    near.join(", ")
+                ),
+            }
+            std::process::exit(1);
+        }
+        if args.select_1
+            && let Some(i) = search::select_one(&hits, &list, query)
+        {
+            connect(&list[i]);
+        }
+    }
     // Set up signal handler for SIGHUP
-    let mut signals = Signals::new(&[SIGINT, SIGQUIT, SIGTERM,SIGHUP])?;
+    let mut signals = Signals::new([SIGINT, SIGQUIT, SIGTERM, SIGHUP])?;
     // File to write to when connection is lost
 
     // Wait for signals in a separate thread
@@ -56,7 +136,14 @@ fn main() -> io::Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let res = run_app(&mut terminal, arg);
+    let res = run_app(
+        &mut terminal,
+        &list,
+        &mut search,
+        &history,
+        &settings,
+        args.query,
+    );
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
@@ -68,71 +155,175 @@ fn main() -> io::Result<()> {
     if let Err(err) = res {
         println!("Error: {:?}", err);
     } else {
-        if let Some(server) = res.unwrap() {
-            let mut stdout = std::io::stdout();
-            // Move cursor to the top-left corner
-            execute!(stdout, MoveTo(0, 0)).unwrap();
+        match res.unwrap() {
+            Some(Picked::Host(server)) => {
+                let mut stdout = std::io::stdout();
+                // Move cursor to the top-left corner
+                execute!(stdout, MoveTo(0, 0)).unwrap();
 
-            // Clear the entire screen
-            execute!(stdout, Clear(ClearType::All)).unwrap();
+                // Clear the entire screen
+                execute!(stdout, Clear(ClearType::All)).unwrap();
 
-            let _ = Command::new("ssh").arg(server.alias).exec();
-            std::process::exit(1);
+                connect(&server);
+            }
+            Some(Picked::Batch(hosts, batch)) => run_batch(&hosts, &batch),
+            None => {}
         }
     }
     Ok(())
 }
 
+/// Two clicks on the same row this close together connect to its host.
+const DOUBLE_CLICK: Duration = Duration::from_millis(300);
+/// Rows a turn of the mouse wheel scrolls the table by.
+const WHEEL_ROWS: isize = 3;
+
+/// Replaces gossh with `ssh` to `host`, recording the connection first.
+fn connect(host: &list::List) -> ! {
+    if let Some(path) = History::path() {
+        // losing a history entry must never keep you from connecting
+        let _ = History::record(&path, &host.alias, history::now());
+    }
+    let error = Command::new("ssh").arg(&host.alias).exec();
+    eprintln!("gossh: ssh: {error}");
+    std::process::exit(1);
+}
+
+/// Carries out `batch` on `hosts`, recording a connection to each unless
+/// only printing them, and exits with 1 when any command failed. The
+/// output of a command run on the hosts comes under a heading per host.
+fn run_batch(hosts: &[list::List], batch: &Batch) -> ! {
+    let aliases: Vec<&str> = hosts.iter().map(|host| host.alias.as_str()).collect();
+    if *batch == Batch::Print {
+        for alias in &aliases {
+            println!("{alias}");
+        }
+        std::process::exit(0);
+    }
+    if let Some(path) = History::path() {
+        for alias in &aliases {
+            let _ = History::record(&path, alias, history::now());
+        }
+    }
+    let mut failed = false;
+    for (i, mut command) in batch::commands(batch, &aliases).into_iter().enumerate() {
+        let name = match batch {
+            Batch::Run(_) => {
+                let gap = if i == 0 { "" } else { "\n" };
+                println!("{gap}==> {} <==", aliases[i]);
+                aliases[i].to_owned()
+            }
+            _ => command.get_program().to_string_lossy().into_owned(),
+        };
+        match command.status() {
+            Ok(status) if status.success() => {}
+            Ok(status) => {
+                eprintln!("gossh: {name}: {status}");
+                failed = true;
+            }
+            Err(error) => {
+                eprintln!("gossh: {name}: {error}");
+                failed = true;
+            }
+        }
+    }
+    std::process::exit(i32::from(failed));
+}
+
+/// What the config file sets up for the picker.
+struct Settings {
+    layouts: Layouts,
+    /// The columns shown at start.
+    columns: Vec<Column>,
+    theme: Theme,
+    bindings: Bindings,
+    navigation: Navigation,
+}
+
+impl Settings {
+    fn new(config: &Config) -> Result<Settings, String> {
+        Ok(Settings {
+            layouts: config.layouts()?,
+            columns: config.columns()?,
+            theme: config.theme(Depth::detect())?,
+            bindings: config.bindings()?,
+            navigation: config.navigation(),
+        })
+    }
+}
+
 fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
+    list: &[list::List],
+    search: &mut Search,
+    history: &History,
+    settings: &Settings,
     arg: Option<String>,
-) -> io::Result<Option<list::List>> {
-    let config_file = Server::get_list();
-    let (_, raw_list) = Server::parse_list(&config_file).unwrap();
-    let list: Vec<list::List> = Server::hash_list(raw_list);
+) -> io::Result<Option<Picked>> {
+    let theme = &settings.theme;
+    let navigation = &settings.navigation;
+    let mut columns = settings.columns.clone();
     let mut textarea = TextArea::default();
-    let mut last_click_time: Option<Instant> = None;
-    let mut last_click_position: Option<(u16, u16)> = None;
-    let double_click_threshold = Duration::from_millis(300); // 300ms for a double-click
-    let mut height = None;
+    // when the last click was and on which row
+    let mut last_click: Option<(Instant, usize)> = None;
+    // where the last frame drew the table's rows, the details pane and the search box
+    let mut rows_area = Rect::default();
+    let mut preview_rect: Option<Rect> = None;
+    let mut search_area = Rect::default();
     textarea.set_block(Block::default().title("Search").borders(Borders::ALL));
-    let mut list_state = ListState::default();
-
-    list_state.select(Some(0)); // Start with first item selected
-
-    let mut binding = list.clone();
-    match arg {
-        Some(argument) => {
-            textarea.insert_str(argument);
-            let search_query = textarea.lines().join("\n");
-            binding = binding
-                .iter()
-                .filter(|a| {
-                    num_extract(&a.display_name).contains(&num_extract(&search_query))
-                        && char_extract(&a.display_name).contains(&char_extract(&search_query))
-                })
-                .cloned()
-                .collect();
-            for item in &mut binding {
-                //   item.score = normalized_damerau_levenshtein(
-                //       &search_query,
-                //     &item.hostname.split(".").next().unwrap(),
-                //   );
-                item.score = strsim::jaro_winkler(&search_query, &item.display_name);
-            }
-            binding.sort_by(|a, b| {
-                b.score
-                    .partial_cmp(&a.score)
-                    .unwrap_or(std::cmp::Ordering::Equal)
-            });
-            //println!("{}",format!("{:#?}", search_query).chars().filter(|c| !c.is_whitespace()).collect::<String>());
-            //std::thread::sleep(std::time::Duration::from_millis(3000));
-
-            list_state.select(Some(0)); // Start with first item selected
-        }
-        _ => {}
+    let mut table_state = TableState::default();
+    let now = history::now();
+    let natural: Vec<u16> = Column::ALL
+        .iter()
+        .map(|column| column.natural_width(list, history, now))
+        .collect();
+    let natural_width = |column: &Column| natural[*column as usize];
+    // hosts sorted by this column instead of relevance
+    let mut sort: Option<Column> = None;
+    let mut preview_shown = false;
+    let mut preview_scroll = ScrollViewState::default();
+    // the ssh config with its includes spliced in, read when first needed
+    let mut config_text: Option<String> = None;
+    // the details pane's lines and the host they are for
+    let mut details: Option<(usize, Vec<Line>)> = None;
+    let mut mode = Mode::Insert;
+    // hosts marked for a batch action, by index into `list`
+    let mut marked: BTreeSet<usize> = BTreeSet::new();
+    let choices = Choice::offered(batch::in_tmux());
+    let mut menu: Option<Menu> = None;
+
+    table_state.select(Some(0)); // Start with first item selected
+
+    // the hits as the last search ordered them, and `binding` as shown,
+    // sorted by the sort column if any
+    let mut ranked = search.run(&Query::And(Vec::new()));
+    let mut binding = ranked.clone();
+    // whether `binding` holds suggestions because nothing matched
+    let mut suggested = false;
+    if let Some(argument) = arg {
+        textarea.insert_str(argument);
+        let search_query = textarea.lines().join("\n");
+        suggested = refilter(search, settings, &mut textarea, &search_query, &mut ranked)
+            .unwrap_or(suggested);
+        binding = ranked.clone();
+        sort_hits(&mut binding, sort, list, history);
+        table_state.select(Some(0)); // Start with first item selected
     }
     loop {
+        let selected = table_state
+            .selected()
+            .and_then(|i| binding.get(i))
+            .map(|hit| hit.index);
+        if preview_shown
+            && let Some(index) = selected
+            && details.as_ref().is_none_or(|(shown, _)| *shown != index)
+        {
+            let config = config_text.get_or_insert_with(|| {
+                Server::read_config(&Server::config_path(), &mut Vec::new(), &mut Vec::new())
+            });
+            details = Some((index, preview::details(&list[index], config, history, now)));
+            preview_scroll = ScrollViewState::default();
+        }
         terminal.draw(|f| {
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
@@ -141,226 +332,437 @@ fn run_app<B: Backend>(
                     Constraint::Length(3), // Search Box
                 ])
                 .split(f.area());
-            height = Some(chunks[0].height.saturating_sub(2));
+            // the details pane goes beside the table when there's room, else below
+            let (table_area, preview_area) = if preview_shown {
+                let direction = if chunks[0].width >= 100 {
+                    Direction::Horizontal
+                } else {
+                    Direction::Vertical
+                };
+                let split = Layout::default()
+                    .direction(direction)
+                    .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                    .split(chunks[0]);
+                (split[0], Some(split[1]))
+            } else {
+                (chunks[0], None)
+            };
+            // the rows go between the borders, under the header
+            let inner = Block::default().borders(Borders::ALL).inner(table_area);
+            rows_area = Rect {
+                y: inner.y + 1,
+                height: inner.height.saturating_sub(1),
+                ..inner
+            };
+            preview_rect = preview_area;
+            search_area = chunks[1];
+            navigation.scroll_into_view(&mut table_state, binding.len(), rows_area.height.into());
             //binding.push(list::List::default(format!("{}, {}", chunks[0].height, chunks[1].height)));
             // Search Box/
-            let list_items: Vec<ListItem> = binding
-                .iter_mut()
+            // the first column grows to fit the labels saying what matched
+            let mut first_width = 0;
+            let rows: Vec<Row> = binding
+                .iter()
                 .enumerate()
-                .map(|(i, item)| {
-                    let style = match list_state.selected() {
-                        Some(current) => {
-                            if i == current {
-                                Style::default().fg(Color::Yellow)
-                            } else {
-                                let set;
-                                if current > i {
-                                    set = current - i;
-                                } else {
-                                    set = i - current;
-                                }
-                                let dim_factor;
-                                if (set as u32 * 20) < 80 {
-                                    dim_factor = set as u8 * 20; // Dims each item more as the index increases
-                                } else {
-                                    dim_factor = 80;
-                                }
-                                let dim_color = Color::Rgb(
-                                    255 - dim_factor,
-                                    255 - dim_factor,
-                                    255 - dim_factor,
-                                ); // Dimmer color
-
-                                Style::default().fg(dim_color)
-                            }
-                        }
-                        None => Style::default().fg(Color::White),
-                    };
-                    ListItem::new(item.display_name.clone()).style(style)
+                .map(|(i, hit)| {
+                    // rows fade the further they are from the selection
+                    let distance = table_state.selected().map(|current| current.abs_diff(i));
+                    let (style, matched) = (theme.row(distance), theme.matched(distance));
+                    let item = &list[hit.index];
+                    let found = &hit.matched;
+                    let mut cells: Vec<Line> = columns
+                        .iter()
+                        .map(|column| {
+                            let text = column.cell(item, history, now);
+                            table::highlight(&text, &column.positions(item, found), matched)
+                        })
+                        .collect();
+                    // say why a host is listed when no column shows what matched
+                    if found.field != Field::Name
+                        && !columns.iter().any(|c| c.field() == Some(found.field))
+                    {
+                        let label = table::highlight(&found.text, &found.positions, matched);
+                        let first = &mut cells[0].spans;
+                        first.push(Span::raw(format!("  ({}: ", found.field.label())));
+                        first.extend(label.spans);
+                        first.push(Span::raw(")"));
+                        first_width = first_width.max(cells[0].width() as u16);
+                    }
+                    if marked.contains(&hit.index) {
+                        Row::new(cells).style(theme.marked(distance))
+                    } else {
+                        Row::new(cells).style(style)
+                    }
                 })
                 .collect();
-
-            let widget_list = List::new(list_items.clone())
-                .block(
-                    Block::default()
-                        .title("Limoo Host Servers")
-                        .borders(Borders::ALL),
-                )
+            let header = Row::new(columns.iter().map(|&column| {
+                if sort == Some(column) {
+                    format!("{} ▾", column.name())
+                } else {
+                    column.name().to_owned()
+                }
+            }))
+            .style(Style::default().add_modifier(Modifier::BOLD));
+            let mut natural: Vec<u16> = columns.iter().map(natural_width).collect();
+            natural[0] = natural[0].max(first_width);
+            // the borders, the highlight symbol and a space between columns
+            let spare = 2 + 3 + columns.len() as u16 - 1;
+            let widths = table::widths(&natural, table_area.width.saturating_sub(spare));
+
+            let block = if suggested {
+                Block::default()
+                    .title("No host matches, did you mean")
+                    .title_style(theme.suggestion())
+            } else {
+                Block::default().title("Limoo Host Servers")
+            };
+            let block = if mode == Mode::Normal {
+                block.title(Line::from(" NORMAL ").right_aligned())
+            } else {
+                block
+            };
+            let block = if marked.is_empty() {
+                block
+            } else {
+                block.title(Line::from(format!(" {} marked ", marked.len())).right_aligned())
+            };
+            let widget_table = Table::new(rows, widths.into_iter().map(Constraint::Length))
+                .header(header)
+                .block(block.borders(Borders::ALL))
                 .highlight_symbol(">> ");
 
-            //f.render_widget(list, chunks[0]);
-            f.render_stateful_widget(widget_list, chunks[0], &mut list_state);
+            f.render_stateful_widget(widget_table, table_area, &mut table_state);
+            if let Some(area) = preview_area {
+                let block = Block::default().title("Details").borders(Borders::ALL);
+                let inner = block.inner(area);
+                f.render_widget(block, area);
+                if let Some((shown, lines)) = &details
+                    && selected == Some(*shown)
+                {
+                    // a column is left for the scrollbar
+                    let size = Size::new(inner.width.saturating_sub(1), lines.len() as u16);
+                    let mut view = ScrollView::new(size)
+                        .horizontal_scrollbar_visibility(ScrollbarVisibility::Never);
+                    view.render_widget(Paragraph::new(lines.clone()), view.area());
+                    f.render_stateful_widget(view, inner, &mut preview_scroll);
+                }
+            }
             f.render_widget(&textarea, chunks[1]);
+            match &mut menu {
+                Some(Menu::Choose(state)) => {
+                    let area = centered(f.area(), 40, choices.len() as u16 + 2);
+                    let items = choices
+                        .iter()
+                        .enumerate()
+                        .map(|(i, choice)| format!("{} {}", i + 1, choice.label()));
+                    let choice_list = List::new(items)
+                        .block(
+                            Block::default()
+                                .title(format!("{} marked hosts", marked.len()))
+                                .borders(Borders::ALL),
+                        )
+                        .highlight_style(theme.row(Some(0)))
+                        .highlight_symbol(">> ");
+                    f.render_widget(ratatui::widgets::Clear, area);
+                    f.render_stateful_widget(choice_list, area, state);
+                }
+                Some(Menu::Command(input)) => {
+                    let area = centered(f.area(), 60, 3);
+                    f.render_widget(ratatui::widgets::Clear, area);
+                    f.render_widget(&**input, area);
+                }
+                None => {}
+            }
         })?;
 
         if event::poll(std::time::Duration::from_millis(150))? {
             let event = event::read()?;
+            // the menu takes the keys while it's open
+            if let Some(open) = menu.take() {
+                if let Event::Key(key) = event {
+                    let hosts = || marked.iter().map(|&i| list[i].clone()).collect();
+                    match menu_key(open, &key, &choices) {
+                        MenuOutcome::Open(open) => menu = Some(open),
+                        MenuOutcome::Closed => {}
+                        MenuOutcome::Picked(batch) => {
+                            return Ok(Some(Picked::Batch(hosts(), batch)));
+                        }
+                    }
+                } else {
+                    menu = Some(open);
+                }
+                continue;
+            }
             if let Event::Mouse(mouse_event) = event {
+                let position = Position::new(mouse_event.column, mouse_event.row);
                 match mouse_event.kind {
-                    MouseEventKind::Down(_) => {
-                        let (x, y) = (mouse_event.column, mouse_event.row);
-
-                        if y > 0 && y - 1 < height.expect("Idon't know") {
-                            list_state
-                                .select(Some((list_state.offset() + (y - 1) as usize) as usize));
-
-                            let current_time = Instant::now();
-                            if let Some(last_time) = last_click_time {
-                                // Check if time difference between clicks is within double-click threshold
-                                if current_time.duration_since(last_time) <= double_click_threshold
-                                {
-                                    if let Some((last_x, last_y)) = last_click_position {
-                                        if last_x == x && last_y == y {
-                                            // Double-click detected on the same position
-                                            //return Ok(Some(filtered_answers[selected_index].clone()));
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-
-                            // Update the last click time and position
-                            last_click_time = Some(current_time);
-                            last_click_position = Some((x, y));
-                        }
-                        // You need to determine if (x, y) is within the list widget.
-                        // If so, map `y` to list index and update your selection.
+                    MouseEventKind::Down(MouseButton::Left) if search_area.contains(position) => {
+                        mode = switch(&mut textarea, Mode::Insert);
+                        // the text starts inside the border
+                        let column = position.x.saturating_sub(search_area.x + 1);
+                        textarea.move_cursor(CursorMove::Jump(0, column));
                     }
-                    MouseEventKind::ScrollUp => {
-                        list_state.select_previous();
+                    MouseEventKind::Down(MouseButton::Left) => {
+                        let Some(row) =
+                            Navigation::row_at(&table_state, binding.len(), rows_area, position)
+                        else {
+                            continue;
+                        };
+                        let now = Instant::now();
+                        let double = last_click.is_some_and(|(time, last)| {
+                            last == row && now.duration_since(time) <= DOUBLE_CLICK
+                        });
+                        table_state.select(Some(row));
+                        if double {
+                            break;
+                        }
+                        last_click = Some((now, row));
                     }
-                    MouseEventKind::ScrollDown => {
-                        list_state.select_next();
+                    MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                        let up = mouse_event.kind == MouseEventKind::ScrollUp;
+                        if preview_rect.is_some_and(|area| area.contains(position)) {
+                            if up {
+                                preview_scroll.scroll_up();
+                            } else {
+                                preview_scroll.scroll_down();
+                            }
+                        } else {
+                            let by = if up { -WHEEL_ROWS } else { WHEEL_ROWS };
+                            navigation.scroll(
+                                &mut table_state,
+                                binding.len(),
+                                rows_area.height.into(),
+                                by,
+                            );
+                        }
                     }
                     _ => {}
                 }
             } else if let Event::Key(key) = event {
-                match key.code {
-                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        return Ok(None);
+                // rows the table showed last
+                let page = usize::from(rows_area.height);
+                match settings.bindings.action(mode, &key) {
+                    Some(Action::Cancel) => return Ok(None),
+                    Some(Action::Accept) if !marked.is_empty() => {
+                        menu = Some(Menu::Choose(ListState::default().with_selected(Some(0))));
                     }
-                    KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        return Ok(None);
+                    Some(Action::Accept) if !binding.is_empty() => break,
+                    Some(Action::Accept) => {}
+                    Some(Action::Down) => navigation.down(&mut table_state, binding.len()),
+                    Some(Action::Up) => navigation.up(&mut table_state, binding.len()),
+                    Some(Action::PageUp) => {
+                        navigation.page_up(&mut table_state, binding.len(), page);
                     }
-                    //KeyCode::Char('h') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    //    return Ok(None);
-                    //}
-                    KeyCode::Esc => return Ok(None),
-                    KeyCode::Enter => {
-                        if binding.len() == 0 {
-                            continue;
-                        }
-                        break;
-                    }
-                    KeyCode::Down => {
-                        list_state.select_next();
+                    Some(Action::PageDown) => {
+                        navigation.page_down(&mut table_state, binding.len(), page);
                     }
-                    KeyCode::Up => {
-                        list_state.select_previous();
+                    Some(Action::First) => navigation.first(&mut table_state, binding.len()),
+                    Some(Action::Last) => navigation.last(&mut table_state, binding.len()),
+                    Some(Action::TogglePreview) => preview_shown = !preview_shown,
+                    Some(Action::PreviewUp) => preview_scroll.scroll_up(),
+                    Some(Action::PreviewDown) => preview_scroll.scroll_down(),
+                    Some(Action::Sort) => {
+                        sort = table::next_sort(sort, &columns);
+                        table_state.select(Some(0));
+                        binding = ranked.clone();
+                        sort_hits(&mut binding, sort, list, history);
                     }
-                    KeyCode::Home | KeyCode::PageUp => {
-                        list_state.select(Some(list_state.offset()));
-                    }
-                    KeyCode::End | KeyCode::PageDown => {
-                        list_state.select(Some(list_state.offset()));
-                        if let Some(height) = height {
-                            list_state.scroll_down_by(height - 1);
+                    Some(Action::ToggleColumn(column)) => {
+                        table::toggle(&mut columns, column);
+                        if !columns.contains(&column) && sort == Some(column) {
+                            sort = None;
                         }
+                        binding = ranked.clone();
+                        sort_hits(&mut binding, sort, list, history);
                     }
-
-                    KeyCode::Backspace if !textarea.lines().join("").is_empty() => {
-                        list_state.select(Some(0));
-                    }
-                    KeyCode::Char('h') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        if !textarea.lines().join("").is_empty() {
-                            list_state.select(Some(0));
+                    Some(Action::ToggleMark) => {
+                        if let Some(hit) = table_state.selected().and_then(|i| binding.get(i)) {
+                            if !marked.remove(&hit.index) {
+                                marked.insert(hit.index);
+                            }
+                            navigation.down(&mut table_state, binding.len());
                         }
                     }
-
-                    KeyCode::Char(_) => {
-                        list_state.select(Some(0));
+                    Some(Action::NormalMode) => mode = switch(&mut textarea, Mode::Normal),
+                    Some(Action::InsertMode) => mode = switch(&mut textarea, Mode::Insert),
+                    // unbound keys edit the query in insert mode
+                    None if mode == Mode::Insert => {
+                        let before = textarea.lines().join("\n");
+                        textarea.input(key);
+                        let search_query = textarea.lines().join("\n");
+                        if search_query != before {
+                            table_state.select(Some(0));
+                            // Filter answers based on the search query
+                            suggested = refilter(
+                                search,
+                                settings,
+                                &mut textarea,
+                                &search_query,
+                                &mut ranked,
+                            )
+                            .unwrap_or(suggested);
+                            binding = ranked.clone();
+                            sort_hits(&mut binding, sort, list, history);
+                        }
                     }
-
-                    _ => {}
+                    None => {}
                 }
-
-                textarea.input(key);
-            }
-            let search_query = tools::persian_to_qwerty(&textarea.lines().join("\n"));
-            // Filter answers based on the search query
-            binding = list
-                .clone()
-                .iter()
-                .filter(|a| {
-                    num_extract(&a.display_name).contains(&num_extract(&search_query))
-                        && char_extract(&a.display_name).contains(&char_extract(&search_query))
-                })
-                .cloned()
-                .collect();
-            for item in &mut binding {
-                //   item.score = normalized_damerau_levenshtein(
-                //       &search_query,
-                //     &item.hostname.split(".").next().unwrap(),
-                //   );
-                item.score = strsim::jaro_winkler(&search_query, &item.display_name);
             }
-            binding.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
         }
     }
-    match list_state.selected() {
-        Some(i) => Ok(Some(binding[i].clone())),
+    match table_state.selected() {
+        Some(i) => Ok(Some(Picked::Host(list[binding[i].index].clone()))),
         None => Ok(None),
     }
 }
 
-fn num_extract(name: &str) -> String {
-    name.chars()
-        .filter(|a| *a >= '0' && *a <= '9')
-        .collect::<String>()
-        .to_owned()
-}
-fn char_extract(name: &str) -> String {
-    name.chars()
-        .filter(|a| (*a >= 'a' && *a <= 'z') || (*a >= 'A' && *a <= 'Z'))
-        .collect::<String>()
-        .to_owned()
+/// What was picked: a host to connect to, or the marked hosts, in the
+/// order of the list, and what to do with them.
+enum Picked {
+    Host(list::List),
+    Batch(Vec<list::List>, Batch),
 }
 
-#[test]
-fn char_extract_test() {
-    let server = Server {};
-    let config_file = Server::get_list();
-    let (_, list) = Server::parse_list(&config_file).unwrap();
+/// The menu Enter opens when hosts are marked.
+enum Menu {
+    /// Choosing from `batch::Choice::offered`.
+    Choose(ListState),
+    /// Asking for the command to run on each host.
+    Command(Box<TextArea<'static>>),
+}
 
-    let list = Server::hash_list(list);
-    for i in list {
-        let name = i.display_name;
+/// What a key did to the menu.
+enum MenuOutcome {
+    /// The menu to show next.
+    Open(Menu),
+    Closed,
+    Picked(Batch),
+}
 
-        println!("{}: {}", name, char_extract(&name));
+/// Handles `key` in `menu`.
+fn menu_key(menu: Menu, key: &KeyEvent, choices: &[Choice]) -> MenuOutcome {
+    let cancel = key.code == KeyCode::Esc
+        || key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+    match menu {
+        Menu::Choose(_) if cancel => MenuOutcome::Closed,
+        Menu::Choose(mut state) => {
+            let picked = match key.code {
+                KeyCode::Up | KeyCode::Char('k') => {
+                    state.select_previous();
+                    None
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    state.select(state.selected().map(|i| (i + 1).min(choices.len() - 1)));
+                    None
+                }
+                KeyCode::Enter => state.selected(),
+                KeyCode::Char(c @ '1'..='9') => Some(c as usize - '1' as usize),
+                _ => None,
+            };
+            match picked.and_then(|i| choices.get(i)) {
+                Some(Choice::Windows) => MenuOutcome::Picked(Batch::Windows),
+                Some(Choice::Panes) => MenuOutcome::Picked(Batch::Panes),
+                Some(Choice::Print) => MenuOutcome::Picked(Batch::Print),
+                Some(Choice::Run) => {
+                    let mut input = TextArea::default();
+                    input.set_block(
+                        Block::default()
+                            .title("Command to run on each host")
+                            .borders(Borders::ALL),
+                    );
+                    MenuOutcome::Open(Menu::Command(Box::new(input)))
+                }
+                None => MenuOutcome::Open(Menu::Choose(state)),
+            }
+        }
+        // back to the choices
+        Menu::Command(_) if cancel => {
+            MenuOutcome::Open(Menu::Choose(ListState::default().with_selected(Some(0))))
+        }
+        Menu::Command(mut input) => {
+            let command = input.lines().join(" ");
+            match key.code {
+                KeyCode::Enter if !command.trim().is_empty() => {
+                    MenuOutcome::Picked(Batch::Run(command))
+                }
+                KeyCode::Enter => MenuOutcome::Open(Menu::Command(input)),
+                _ => {
+                    input.input(*key);
+                    MenuOutcome::Open(Menu::Command(input))
+                }
+            }
+        }
     }
 }
-#[test]
-fn num_extract_test() {
-    let server = Server {};
-    let config_file = Server::get_list();
-    let (_, list) = Server::parse_list(&config_file).unwrap();
-
-    let list = Server::hash_list(list);
-    for i in list {
-        let name = i.display_name;
 
-        println!("{}: {}", name, num_extract(&name));
+/// `area`'s middle, `width` by `height` at most.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let (width, height) = (width.min(area.width), height.min(area.height));
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
     }
 }
 
-/*
-the right way to implement the functionality of gossh is to split
-numbers from alphabet charcters in the input.
-For example 100ooz input definitely should have highest score with yooz100,
-but since the number is in the beginning, it matches pirouz100
-*/
+/// Switches the picker to `mode`, showing the search box's cursor only
+/// while the keys type into it.
+fn switch(textarea: &mut TextArea, mode: Mode) -> Mode {
+    let cursor = match mode {
+        Mode::Insert => Style::default().add_modifier(Modifier::REVERSED),
+        Mode::Normal => Style::default(),
+    };
+    textarea.set_cursor_style(cursor);
+    mode
+}
 
-/*
-TODO: mouse click resets from beginning, backspace when input is empty
+/// Re-filters the hosts for `query`. When it was read another way, typed
+/// with another layout active or as Persian for Finglish, the search box
+/// title says how and what it was read as. When nothing matches, the
+/// nearest hosts are listed instead and `Some(true)` is returned. A
+/// query that doesn't parse keeps the current results, returning `None`,
+/// and puts the syntax error in the search box title.
+fn refilter(
+    search: &mut Search,
+    settings: &Settings,
+    textarea: &mut TextArea,
+    query: &str,
+    binding: &mut Vec<Hit>,
+) -> Option<bool> {
+    match search.run_typed(query, &settings.layouts) {
+        Ok(found) => {
+            let suggested = found.hits.is_empty();
+            *binding = if suggested {
+                search.suggest(query)
+            } else {
+                found.hits
+            };
+            let block = match found.reading {
+                Some((reading, text)) => Block::default()
+                    .title(format!("Search [{reading}: {text}]"))
+                    .title_style(settings.theme.reading()),
+                None => Block::default().title("Search"),
+            };
+            textarea.set_block(block.borders(Borders::ALL));
+            Some(suggested && !binding.is_empty())
+        }
+        Err(error) => {
+            textarea.set_block(
+                Block::default()
+                    .title(format!("Search: {error}"))
+                    .title_style(settings.theme.error())
+                    .borders(Borders::ALL),
+            );
+            None
+        }
+    }
+}
 
-*/
+/// Orders `hits` by the `sort` column, relevance breaking ties; with no
+/// sort column they stay in relevance order.
+fn sort_hits(hits: &mut [Hit], sort: Option<Column>, list: &[list::List], history: &History) {
+    if let Some(column) = sort {
+        hits.sort_by_cached_key(|hit| column.sort_key(&list[hit.index], history));
+    }
+}
@@ -0,0 +1,331 @@
+//! Moving the selection through the host table and keeping it in view.
+//! Pages are as tall as the rows the table shows.
+use ratatui::layout::{Position, Rect};
+use ratatui::widgets::TableState;
+
+/// Rows kept visible above and below the selection by default.
+pub const SCROLL_OFF: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Navigation {
+    /// Whether moving a row past either end goes round to the other.
+    pub wrap: bool,
+    /// Rows kept visible above and below the selection, when the page has
+    /// room for them.
+    pub scroll_off: usize,
+}
+
+impl Default for Navigation {
+    fn default() -> Navigation {
+        Navigation {
+            wrap: false,
+            scroll_off: SCROLL_OFF,
+        }
+    }
+}
+
+impl Navigation {
+    pub fn up(&self, state: &mut TableState, len: usize) {
+        match state.selected() {
+            _ if len == 0 => {}
+            Some(0) if self.wrap => state.select(Some(len - 1)),
+            Some(i) => state.select(Some(i.min(len - 1).saturating_sub(1))),
+            None => state.select(Some(0)),
+        }
+    }
+    pub fn down(&self, state: &mut TableState, len: usize) {
+        match state.selected() {
+            _ if len == 0 => {}
+            Some(i) if i + 1 >= len && self.wrap => state.select(Some(0)),
+            Some(i) => state.select(Some((i + 1).min(len - 1))),
+            None => state.select(Some(0)),
+        }
+    }
+    /// Moves the selection and the view up by `page` rows, stopping at
+    /// the first row; pages never wrap.
+    pub fn page_up(&self, state: &mut TableState, len: usize, page: usize) {
+        if len == 0 {
+            return;
+        }
+        let selected = state.selected().unwrap_or(0).min(len - 1);
+        *state.offset_mut() = state.offset().saturating_sub(page);
+        state.select(Some(selected.saturating_sub(page.max(1))));
+    }
+    /// Moves the selection and the view down by `page` rows, stopping at
+    /// the last row.
+    pub fn page_down(&self, state: &mut TableState, len: usize, page: usize) {
+        if len == 0 {
+            return;
+        }
+        let selected = state.selected().unwrap_or(0);
+        *state.offset_mut() = (state.offset() + page).min(len.saturating_sub(page));
+        state.select(Some((selected + page.max(1)).min(len - 1)));
+    }
+    pub fn first(&self, state: &mut TableState, len: usize) {
+        if len > 0 {
+            state.select(Some(0));
+        }
+    }
+    pub fn last(&self, state: &mut TableState, len: usize) {
+        if len > 0 {
+            state.select(Some(len - 1));
+        }
+    }
+    /// Scrolls the view `by` rows, negative for up, taking the selection
+    /// along only as far as it has to stay in view.
+    pub fn scroll(&self, state: &mut TableState, len: usize, page: usize, by: isize) {
+        if len == 0 || page == 0 {
+            return;
+        }
+        let last_offset = len.saturating_sub(page);
+        let offset = state.offset().saturating_add_signed(by).min(last_offset);
+        *state.offset_mut() = offset;
+        let Some(selected) = state.selected() else {
+            return;
+        };
+        // the rows `scroll_into_view` leaves where they are
+        let margin = self.scroll_off.min((page - 1) / 2);
+        let top = if offset == 0 { 0 } else { offset + margin };
+        let bottom = if offset == last_offset {
+            len - 1
+        } else {
+            offset + page - 1 - margin
+        };
+        state.select(Some(selected.clamp(top, bottom)));
+    }
+    /// The row shown at `position` of `rows`, the area the table draws its
+    /// rows in, if any is.
+    pub fn row_at(state: &TableState, len: usize, rows: Rect, position: Position) -> Option<usize> {
+        if !rows.contains(position) {
+            return None;
+        }
+        let row = state.offset() + (position.y - rows.y) as usize;
+        (row < len).then_some(row)
+    }
+    /// Scrolls the view of a table `page` rows tall as little as keeps the
+    /// selection in it, with `scroll_off` rows around it where there are
+    /// any. Call before drawing: the table itself only scrolls to the
+    /// selection, without a margin.
+    pub fn scroll_into_view(&self, state: &mut TableState, len: usize, page: usize) {
+        let Some(selected) = state.selected() else {
+            return;
+        };
+        if page == 0 || len == 0 {
+            return;
+        }
+        let selected = selected.min(len - 1);
+        // a margin over half the page would keep the selection from moving
+        let margin = self.scroll_off.min((page - 1) / 2);
+        let mut offset = state.offset();
+        if selected < offset + margin {
+            offset = selected.saturating_sub(margin);
+        }
+        if selected + margin >= offset + page {
+            offset = selected + margin + 1 - page;
+        }
+        *state.offset_mut() = offset.min(len.saturating_sub(page));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+    use ratatui::layout::Constraint;
+    use ratatui::widgets::{Row, Table};
+
+    const ROWS: usize = 20;
+    /// Lines of the test terminal, all rows as there are no borders.
+    const PAGE: usize = 5;
+
+    /// Draws `ROWS` numbered rows as the picker would, returning the lines
+    /// on screen.
+    fn draw(navigation: &Navigation, state: &mut TableState) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(10, PAGE as u16)).unwrap();
+        terminal
+            .draw(|f| {
+                navigation.scroll_into_view(state, ROWS, PAGE);
+                let rows = (0..ROWS).map(|i| Row::new([i.to_string()]));
+                let table = Table::new(rows, [Constraint::Length(4)]).highlight_symbol(">");
+                f.render_stateful_widget(table, f.area(), state);
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..PAGE as u16)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_owned()
+            })
+            .collect()
+    }
+
+    fn selected(state: &TableState) -> usize {
+        state.selected().unwrap()
+    }
+
+    #[test]
+    fn pages_move_by_what_is_shown() {
+        let navigation = Navigation {
+            scroll_off: 0,
+            ..Navigation::default()
+        };
+        let mut state = TableState::default().with_selected(Some(0));
+        draw(&navigation, &mut state);
+        navigation.page_down(&mut state, ROWS, PAGE);
+        assert_eq!(
+            draw(&navigation, &mut state),
+            [">5", " 6", " 7", " 8", " 9"]
+        );
+        navigation.down(&mut state, ROWS);
+        navigation.page_down(&mut state, ROWS, PAGE);
+        assert_eq!(draw(&navigation, &mut state)[1], ">11");
+        navigation.page_up(&mut state, ROWS, PAGE);
+        assert_eq!(
+            draw(&navigation, &mut state),
+            [" 5", ">6", " 7", " 8", " 9"]
+        );
+
+        // pages stop at the ends
+        for _ in 0..5 {
+            navigation.page_down(&mut state, ROWS, PAGE);
+        }
+        assert_eq!(selected(&state), ROWS - 1);
+        assert_eq!(draw(&navigation, &mut state)[4], ">19");
+        for _ in 0..5 {
+            navigation.page_up(&mut state, ROWS, PAGE);
+        }
+        assert_eq!(draw(&navigation, &mut state)[0], ">0");
+    }
+
+    #[test]
+    fn first_and_last_match() {
+        let navigation = Navigation::default();
+        let mut state = TableState::default().with_selected(Some(7));
+        navigation.last(&mut state, ROWS);
+        assert_eq!(draw(&navigation, &mut state)[4], ">19");
+        navigation.first(&mut state, ROWS);
+        assert_eq!(draw(&navigation, &mut state)[0], ">0");
+
+        let mut empty = TableState::default();
+        navigation.last(&mut empty, 0);
+        navigation.page_down(&mut empty, 0, PAGE);
+        assert_eq!(empty.selected(), None);
+    }
+
+    #[test]
+    fn wraps_only_when_asked() {
+        let mut state = TableState::default().with_selected(Some(0));
+        Navigation::default().up(&mut state, ROWS);
+        assert_eq!(selected(&state), 0);
+
+        let navigation = Navigation {
+            wrap: true,
+            ..Navigation::default()
+        };
+        navigation.up(&mut state, ROWS);
+        assert_eq!(draw(&navigation, &mut state)[4], ">19");
+        navigation.down(&mut state, ROWS);
+        assert_eq!(draw(&navigation, &mut state)[0], ">0");
+        // pages stop at the end even so
+        navigation.last(&mut state, ROWS);
+        navigation.page_down(&mut state, ROWS, PAGE);
+        assert_eq!(selected(&state), ROWS - 1);
+    }
+
+    #[test]
+    fn the_wheel_scrolls_the_view() {
+        let navigation = Navigation {
+            scroll_off: 1,
+            ..Navigation::default()
+        };
+        let mut state = TableState::default().with_selected(Some(2));
+        navigation.scroll(&mut state, ROWS, PAGE, 3);
+        // the selection is taken along to keep a row above it
+        assert_eq!(
+            draw(&navigation, &mut state),
+            [" 3", ">4", " 5", " 6", " 7"]
+        );
+        navigation.scroll(&mut state, ROWS, PAGE, 2);
+        assert_eq!(
+            draw(&navigation, &mut state),
+            [" 5", ">6", " 7", " 8", " 9"]
+        );
+        navigation.scroll(&mut state, ROWS, PAGE, -1);
+        assert_eq!(
+            draw(&navigation, &mut state),
+            [" 4", " 5", ">6", " 7", " 8"]
+        );
+        navigation.scroll(&mut state, ROWS, PAGE, 100);
+        assert_eq!(
+            draw(&navigation, &mut state),
+            [" 15", ">16", " 17", " 18", " 19"]
+        );
+        navigation.scroll(&mut state, ROWS, PAGE, -100);
+        assert_eq!(
+            draw(&navigation, &mut state),
+            [" 0", " 1", " 2", ">3", " 4"]
+        );
+    }
+
+    #[test]
+    fn finds_the_row_under_the_pointer() {
+        let navigation = Navigation::default();
+        let mut state = TableState::default().with_selected(Some(12));
+        let screen = draw(&navigation, &mut state);
+        let rows = Rect::new(0, 0, 10, PAGE as u16);
+        for (y, line) in screen.iter().enumerate() {
+            let row = Navigation::row_at(&state, ROWS, rows, Position::new(3, y as u16));
+            assert_eq!(row.unwrap().to_string(), line[1..]);
+        }
+        assert_eq!(
+            Navigation::row_at(&state, ROWS, rows, Position::new(10, 0)),
+            None
+        );
+        // below the last host
+        assert_eq!(
+            Navigation::row_at(&state, 2, rows, Position::new(0, 3)),
+            None
+        );
+    }
+
+    #[test]
+    fn keeps_rows_around_the_selection() {
+        let navigation = Navigation {
+            scroll_off: 1,
+            ..Navigation::default()
+        };
+        let mut state = TableState::default().with_selected(Some(0));
+        for _ in 0..4 {
+            navigation.down(&mut state, ROWS);
+        }
+        // the row under the selection stays in view
+        assert_eq!(
+            draw(&navigation, &mut state),
+            [" 1", " 2", " 3", ">4", " 5"]
+        );
+        for _ in 0..2 {
+            navigation.up(&mut state, ROWS);
+        }
+        assert_eq!(
+            draw(&navigation, &mut state),
+            [" 1", ">2", " 3", " 4", " 5"]
+        );
+        // no margin past the ends
+        navigation.last(&mut state, ROWS);
+        assert_eq!(draw(&navigation, &mut state)[4], ">19");
+
+        // too big a margin is cut to half the page
+        let navigation = Navigation {
+            scroll_off: 10,
+            ..Navigation::default()
+        };
+        let mut state = TableState::default().with_selected(Some(3));
+        assert_eq!(draw(&navigation, &mut state)[2], ">3");
+        navigation.down(&mut state, ROWS);
+        assert_eq!(draw(&navigation, &mut state)[2], ">4");
+    }
+}
@@ -0,0 +1,190 @@
+//! The form hosts and queries are compared in: compatibility characters
+//! (full-width `ｗｅｂ`, ligatures, `²`) spelled plainly, accents on Latin,
+//! Greek and Cyrillic letters dropped so `munchen` finds `münchen`, and
+//! every script's digits as ASCII ones. Marks other scripts spell with,
+//! like Devanagari vowel signs, are kept.
+//! Case is kept, for the camelCase bonus; compare texts with `fold`.
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+/// First code point of each run of ten decimal digits NFKC leaves alone.
+const ZEROS: &[u32] = &[
+    0x0660, 0x06f0, 0x07c0, 0x0966, 0x09e6, 0x0a66, 0x0ae6, 0x0b66, 0x0be6, 0x0c66, 0x0ce6, 0x0d66,
+    0x0e50, 0x0ed0, 0x0f20, 0x1040, 0x17e0, 0x1810,
+];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Normalized {
+    pub text: String,
+    /// For each char of `text`, the index of the original char it came from.
+    pub origin: Vec<usize>,
+}
+
+impl Normalized {
+    /// Maps char positions in `text` back to the original, ascending and
+    /// without duplicates (`ﬁ` gives two chars of one).
+    pub fn original_positions(&self, positions: &[usize]) -> Vec<usize> {
+        let mut original: Vec<usize> = positions
+            .iter()
+            .filter_map(|&p| self.origin.get(p).copied())
+            .collect();
+        original.dedup();
+        original
+    }
+
+    /// `text` case folded, with each char's origin still in the original.
+    pub fn folded(&self) -> Normalized {
+        let folded = fold(&self.text);
+        Normalized {
+            origin: folded.origin.iter().map(|&i| self.origin[i]).collect(),
+            text: folded.text,
+        }
+    }
+}
+
+/// The chars `c` case folds to: its full lowercase, with `ß` as `ss` and
+/// a final `ς` as `σ`, so `STRASSE` finds `straße`. The dot `İ` keeps in
+/// lowercase is dropped like any accent on a Latin letter.
+pub fn fold_case(c: char) -> impl Iterator<Item = char> {
+    let special = match c {
+        'ß' | 'ẞ' => "ss",
+        'ς' => "σ",
+        _ => "",
+    };
+    let lower = special
+        .is_empty()
+        .then(|| c.to_lowercase().filter(|&c| !is_combining_mark(c)));
+    special.chars().chain(lower.into_iter().flatten())
+}
+
+/// `text` case folded, see `fold_case`, with the index of the char of
+/// `text` each char came from.
+pub fn fold(text: &str) -> Normalized {
+    let mut folded = Normalized::default();
+    for (i, c) in text.chars().enumerate() {
+        for c in fold_case(c) {
+            folded.text.push(c);
+            folded.origin.push(i);
+        }
+    }
+    folded
+}
+
+fn fold_digit(c: char) -> char {
+    let code = c as u32;
+    ZEROS
+        .iter()
+        .find(|&&zero| (zero..zero + 10).contains(&code))
+        .and_then(|zero| char::from_digit(code - zero, 10))
+        .unwrap_or(c)
+}
+
+/// Whether marks on `c` are accents to drop: it is a Latin, Greek or
+/// Cyrillic letter.
+fn takes_accents(c: char) -> bool {
+    c.is_alphabetic()
+        && matches!(c,
+            'A'..='Z' | 'a'..='z' | '\u{c0}'..='\u{24f}' | '\u{370}'..='\u{52f}'
+            | '\u{1c80}'..='\u{1c8f}' | '\u{1e00}'..='\u{1fff}' | '\u{2c60}'..='\u{2c7f}'
+            | '\u{a640}'..='\u{a69f}' | '\u{a720}'..='\u{a7ff}' | '\u{ab30}'..='\u{ab6f}')
+}
+
+/// NFKC with accents dropped. Each char is decomposed on its own, so its
+/// pieces know where they came from, and then composed back with the
+/// marks that follow it.
+pub fn normalize(text: &str) -> Normalized {
+    let mut normalized = Normalized::default();
+    let mut cluster = Normalized::default();
+    let mut base = None;
+    for (i, c) in text.chars().enumerate() {
+        for (k, c) in std::iter::once(c).nfkd().enumerate() {
+            if is_combining_mark(c) {
+                if base.is_some_and(takes_accents) {
+                    continue;
+                }
+            } else {
+                base = Some(c);
+                if k == 0 {
+                    compose(&mut cluster, &mut normalized);
+                }
+            }
+            cluster.text.push(c);
+            cluster.origin.push(i);
+        }
+    }
+    compose(&mut cluster, &mut normalized);
+    normalized
+}
+
+/// Moves `cluster` onto the end of `normalized`, composed. Chars keep
+/// their origin unless some were composed into one, when all take the
+/// first's.
+fn compose(cluster: &mut Normalized, normalized: &mut Normalized) {
+    let composed: Vec<char> = cluster.text.nfc().collect();
+    if composed.len() == cluster.origin.len() {
+        normalized.origin.append(&mut cluster.origin);
+    } else if let Some(&first) = cluster.origin.first() {
+        normalized
+            .origin
+            .extend(std::iter::repeat_n(first, composed.len()));
+    }
+    normalized.text.extend(composed.into_iter().map(fold_digit));
+    cluster.text.clear();
+    cluster.origin.clear();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn text(input: &str) -> String {
+        normalize(input).text
+    }
+
+    #[test]
+    fn folds_forms_accents_and_digits() {
+        assert_eq!(text("ｗｅｂ０１"), "web01");
+        assert_eq!(text("Müller-É"), "Muller-E");
+        // precomposed and combining accents come out the same
+        assert_eq!(text("e\u{301}"), text("é"));
+        assert_eq!(text("۱۰۰ ٣ २"), "100 3 2");
+        assert_eq!(text("ﬁle²"), "file2");
+        assert_eq!(text("web-prod"), "web-prod");
+    }
+
+    #[test]
+    fn keeps_marks_other_scripts_spell_with() {
+        // Devanagari vowel signs and virama, and Hangul syllables, which
+        // decompose into jamo
+        assert_eq!(text("नमस्ते"), "नमस्ते");
+        assert_ne!(text("नमस्ते"), text("नमसत"));
+        assert_eq!(text("한국"), "한국");
+        // accents on Greek and Cyrillic go like Latin ones
+        assert_eq!(text("ά ё"), "α е");
+        let normalized = normalize("e\u{301}x");
+        assert_eq!(
+            (normalized.text.as_str(), &normalized.origin[..]),
+            ("ex", &[0, 2][..])
+        );
+    }
+
+    #[test]
+    fn folds_case_in_full() {
+        let folded = |text: &str| fold(text).text;
+        assert_eq!(folded("Ä"), "ä");
+        assert_eq!(folded("Straße"), folded("STRASSE"));
+        assert_eq!(folded("ẞ"), "ss");
+        assert_eq!(folded("İstanbul"), "istanbul");
+        assert_eq!(folded("ΟΔΟΣ"), folded("οδος"));
+        let folded = normalize("ﬁß").folded();
+        assert_eq!(folded.text, "fiss");
+        assert_eq!(folded.original_positions(&[1, 2, 3]), [0, 1]);
+    }
+
+    #[test]
+    fn positions_map_back() {
+        let normalized = normalize("ﬁé-x");
+        assert_eq!(normalized.text, "fie-x");
+        assert_eq!(normalized.original_positions(&[0, 1, 2, 4]), [0, 1, 3]);
+    }
+}
@@ -0,0 +1,246 @@
+//! The details pane: the options ssh will use for the selected host, the
+//! `Host` block it is defined in, how often it's been used and its notes.
+use crate::history::History;
+use crate::list::{List, Server, wildcard_match};
+use crate::ssh_config::{Body, Config};
+use crate::table::{ago, shorten};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use std::fs;
+use std::path::Path;
+
+/// Options every matching block adds to instead of the first one winning.
+const CUMULATIVE: &[&str] = &[
+    "certificatefile",
+    "dynamicforward",
+    "identityfile",
+    "localforward",
+    "remoteforward",
+    "sendenv",
+];
+
+/// Whether a `Host` line's patterns take in `alias`: one of them matches
+/// it and none of the negated ones do.
+fn host_matches(patterns: &str, alias: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns.split_whitespace() {
+        match pattern.strip_prefix('!') {
+            Some(negated) if wildcard_match(negated, alias) => return false,
+            Some(_) => {}
+            None => matched |= wildcard_match(pattern, alias),
+        }
+    }
+    matched
+}
+
+/// The options ssh uses for `alias` per `config`, the text of a config with
+/// its includes spliced in: the first value of every option from the
+/// blocks whose patterns match, in the order they were found. Keys are
+/// lowercased as `ssh -G` prints them. `Match` blocks other than
+/// `Match all` depend on more than the alias and are left out.
+pub fn effective(config: &str, alias: &str) -> Vec<(String, String)> {
+    let (_, blocks) = Server::parse_list(config).unwrap();
+    let mut options: Vec<(String, String)> = Vec::new();
+    for block in blocks {
+        let mut applies = true;
+        for line in Config::parse(block).lines {
+            let Some((key, value)) = line.key_value() else {
+                continue;
+            };
+            let key = key.to_ascii_lowercase();
+            let value = value.trim_matches('"');
+            match key.as_str() {
+                "host" => applies = host_matches(value, alias),
+                "match" => applies = value.trim().eq_ignore_ascii_case("all"),
+                _ if !applies => {}
+                _ if CUMULATIVE.contains(&key.as_str()) => options.push((key, value.to_owned())),
+                _ => {
+                    if !options.iter().any(|(k, _)| *k == key) {
+                        options.push((key, value.to_owned()));
+                    }
+                }
+            }
+        }
+    }
+    options
+}
+
+/// The lines of the block starting at `line` (1-based) of `path`, up to
+/// the next `Host` or `Match` line, without the blank lines that end it.
+pub fn block(path: &Path, line: usize) -> Option<Vec<String>> {
+    let text = fs::read_to_string(path).ok()?;
+    let mut lines = text.lines().skip(line.checked_sub(1)?);
+    let mut block = vec![lines.next()?.to_owned()];
+    block.extend(
+        lines
+            .take_while(|line| !Server::starts_block(line))
+            .map(str::to_owned),
+    );
+    while block.last().is_some_and(|line| line.trim().is_empty()) {
+        block.pop();
+    }
+    Some(block)
+}
+
+/// Free-form comments of a block: the description and any comment that
+/// isn't a `tags:` or `description:` annotation.
+fn notes(host: &List, block: &[String]) -> Vec<String> {
+    let mut notes = Vec::new();
+    if !host.description.is_empty() {
+        notes.push(host.description.clone());
+    }
+    for line in block {
+        let Body::Comment(comment) = Config::parse(line).lines.remove(0).body else {
+            continue;
+        };
+        let text = comment.trim_start_matches('#').trim();
+        let annotation = text.split_once(':').is_some_and(|(key, _)| {
+            ["tags", "description"].contains(&key.trim().to_ascii_lowercase().as_str())
+        });
+        if !annotation && !text.is_empty() {
+            notes.push(text.to_owned());
+        }
+    }
+    notes
+}
+
+fn heading(text: String) -> Line<'static> {
+    Line::from(Span::styled(
+        text,
+        Style::default().add_modifier(Modifier::BOLD),
+    ))
+}
+
+/// Lines of `key` and `value` pairs, the values lined up.
+fn pairs(pairs: &[(String, String)]) -> Vec<Line<'static>> {
+    let width = pairs
+        .iter()
+        .map(|(k, _)| k.chars().count())
+        .max()
+        .unwrap_or(0);
+    pairs
+        .iter()
+        .map(|(key, value)| Line::from(format!("  {key:width$}  {value}")))
+        .collect()
+}
+
+/// Everything the pane shows for `host`, given the spliced ssh `config`.
+pub fn details(host: &List, config: &str, history: &History, now: u64) -> Vec<Line<'static>> {
+    let mut lines = vec![heading("Effective config".to_owned())];
+    lines.extend(pairs(&effective(config, &host.alias)));
+
+    let block = host
+        .source
+        .as_ref()
+        .and_then(|(path, line)| Some((path, line, block(path, *line)?)));
+    if let Some((path, line, block)) = &block {
+        lines.push(Line::default());
+        lines.push(heading(format!("{}:{line}", shorten(path))));
+        lines.extend(block.iter().map(|text| Line::from(format!("  {text}"))));
+    }
+
+    lines.push(Line::default());
+    lines.push(heading("History".to_owned()));
+    let last = history.last(&host.alias);
+    let mut stats = vec![(
+        "connections".to_owned(),
+        history.count(&host.alias).to_string(),
+    )];
+    if let Some(last) = last {
+        stats.push(("last".to_owned(), ago(now.saturating_sub(last))));
+        stats.push((
+            "frecency".to_owned(),
+            format!("{:.2}", history.frecency(&host.alias, now)),
+        ));
+    }
+    lines.extend(pairs(&stats));
+
+    let notes = notes(host, block.as_ref().map_or(&[][..], |(_, _, b)| b));
+    if !notes.is_empty() {
+        lines.push(Line::default());
+        lines.push(heading("Notes".to_owned()));
+        lines.extend(
+            notes
+                .into_iter()
+                .map(|note| Line::from(format!("  {note}"))),
+        );
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+
+    const CONFIG: &str = "\
+User nobody
+Host web1 web-*
+    HostName web1.example.com
+    IdentityFile ~/.ssh/web
+Host * !db
+    User deploy
+    Port 2200
+    IdentityFile ~/.ssh/id
+Match exec true
+    User wrong
+Match all
+    ForwardAgent yes
+";
+
+    fn keys(alias: &str) -> Vec<(String, String)> {
+        effective(CONFIG, alias)
+    }
+
+    fn pair(key: &str, value: &str) -> (String, String) {
+        (key.to_owned(), value.to_owned())
+    }
+
+    #[test]
+    fn first_value_wins_per_matching_block() {
+        assert_eq!(
+            keys("web1"),
+            [
+                pair("user", "nobody"),
+                pair("hostname", "web1.example.com"),
+                pair("identityfile", "~/.ssh/web"),
+                pair("port", "2200"),
+                pair("identityfile", "~/.ssh/id"),
+                pair("forwardagent", "yes"),
+            ]
+        );
+        // `!db` keeps the `Host *` block out
+        assert_eq!(
+            keys("db"),
+            [pair("user", "nobody"), pair("forwardagent", "yes")]
+        );
+    }
+
+    #[test]
+    fn blocks_and_notes() {
+        let path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/annotations.config");
+        let block = block(&path, 8).unwrap();
+        assert_eq!(block.first().unwrap(), "Host db-primary");
+        assert_eq!(block.last().unwrap(), "    # not: an annotation");
+        assert_eq!(block.len(), 5);
+        assert!(super::block(&path, 100).is_none());
+
+        let host = Server::from_path(&path).remove(1);
+        assert_eq!(host.source, Some((path.clone(), 8)));
+        assert_eq!(notes(&host, &block), ["not: an annotation"]);
+
+        let mut history = History::default();
+        history.add("db-primary", 100);
+        let text: Vec<String> = details(&host, "", &history, 100 + 7200)
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        assert!(text.contains(&format!("{}:8", shorten(&path))), "{text:?}");
+        assert!(
+            text.contains(&"  last         2h ago".to_owned()),
+            "{text:?}"
+        );
+        assert_eq!(text.last().unwrap(), "  not: an annotation");
+    }
+}
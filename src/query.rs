@@ -0,0 +1,617 @@
+//! The search box language: `tag:prod user:deploy !db web`.
+//!
+//! Words are matched fuzzily and must all match; `field:value` filters on a
+//! host field, `!` negates, `|` (or `OR`) separates alternatives, parentheses
+//! group and `"quoted phrases"` match literally, spaces included.
+use crate::list::{List, wildcard_match};
+use crate::normalize::{fold, normalize};
+use crate::rank::{Field, HostMatch, Keys, rank_host};
+use crate::scorer::Scorer;
+use nom::bytes::complete::{tag, take_while, take_while1};
+use nom::character::complete::char;
+use nom::combinator::verify;
+use nom::error::{ErrorKind, ParseError};
+use nom::multi::{many1, separated_list1};
+use nom::sequence::{preceded, terminated};
+use nom::{IResult, Parser};
+use std::fmt;
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// Every part must match; the empty query is an empty `And`.
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    Pred(Predicate),
+    /// A word, matched fuzzily against every field.
+    Text(String),
+    /// A quoted phrase, matched literally (ignoring case) against every field.
+    Phrase(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    Tag(String),
+    User(String),
+    Host(String),
+    Alias(String),
+    Desc(String),
+    Port(u16),
+    Net(IpAddr, u8),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    /// Byte offset in the query where the problem was found.
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (column {})", self.message, self.offset + 1)
+    }
+}
+
+/// nom error carrying where it happened and, for the errors we raise
+/// ourselves, a message meant for the user.
+#[derive(Debug)]
+struct Error<'a> {
+    input: &'a str,
+    message: Option<String>,
+}
+
+impl<'a> ParseError<&'a str> for Error<'a> {
+    fn from_error_kind(input: &'a str, _: ErrorKind) -> Self {
+        Error {
+            input,
+            message: None,
+        }
+    }
+    fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+type Res<'a, T> = IResult<&'a str, T, Error<'a>>;
+
+fn fail<'a, T>(input: &'a str, message: impl Into<String>) -> Res<'a, T> {
+    Err(nom::Err::Failure(Error {
+        input,
+        message: Some(message.into()),
+    }))
+}
+
+fn ws(input: &str) -> Res<'_, &str> {
+    take_while(char::is_whitespace).parse(input)
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '(' | ')' | '|' | '"')
+}
+
+fn bare(input: &str) -> Res<'_, &str> {
+    verify(take_while1(is_word_char), |w: &str| {
+        w != "OR" && !w.starts_with('!')
+    })
+    .parse(input)
+}
+
+fn phrase(input: &str) -> Res<'_, String> {
+    let (rest, _) = char('"').parse(input)?;
+    match rest.find('"') {
+        Some(end) => Ok((&rest[end + 1..], rest[..end].to_owned())),
+        None => fail(input, "unclosed `\"`"),
+    }
+}
+
+fn predicate(input: &str) -> Res<'_, Query> {
+    let (rest, name) =
+        terminated(take_while1(|c: char| c.is_ascii_alphabetic()), char(':')).parse(input)?;
+    let value = match phrase(rest) {
+        Err(nom::Err::Error(_)) => bare(rest).map(|(r, v)| (r, v.to_owned())),
+        other => other,
+    };
+    let (after, value) = match value {
+        Ok(value) => value,
+        Err(nom::Err::Error(_)) => return fail(rest, format!("`{name}:` needs a value")),
+        Err(e) => return Err(e),
+    };
+    let predicate = match name.to_ascii_lowercase().as_str() {
+        "tag" => Predicate::Tag(value),
+        "user" => Predicate::User(value),
+        "host" | "hostname" => Predicate::Host(value),
+        "alias" => Predicate::Alias(value),
+        "desc" | "description" => Predicate::Desc(value),
+        "port" => match value.parse() {
+            Ok(port) => Predicate::Port(port),
+            Err(_) => return fail(rest, format!("`{value}` is not a port number")),
+        },
+        "net" => match parse_net(&value) {
+            Some((addr, prefix)) => Predicate::Net(addr, prefix),
+            None => return fail(rest, format!("`{value}` is not a network like 10.0.0.0/8")),
+        },
+        _ => return fail(input, format!("unknown field `{name}`")),
+    };
+    Ok((after, Query::Pred(predicate)))
+}
+
+fn parse_net(value: &str) -> Option<(IpAddr, u8)> {
+    let (addr, prefix) = match value.split_once('/') {
+        Some((addr, prefix)) => (addr.parse().ok()?, prefix.parse().ok()?),
+        None => {
+            let addr: IpAddr = value.parse().ok()?;
+            (addr, if addr.is_ipv4() { 32 } else { 128 })
+        }
+    };
+    let bits = if addr.is_ipv4() { 32 } else { 128 };
+    (prefix <= bits).then_some((addr, prefix))
+}
+
+fn group(input: &str) -> Res<'_, Query> {
+    let (rest, _) = char('(').parse(input)?;
+    let (rest, query) = match preceded(ws, or_expr).parse(rest) {
+        Err(nom::Err::Error(_)) => return fail(input, "empty `( )`"),
+        other => other?,
+    };
+    match preceded(ws, char::<_, Error>(')')).parse(rest) {
+        Ok((rest, _)) => Ok((rest, query)),
+        Err(_) => fail(input, "missing `)`"),
+    }
+}
+
+fn atom(input: &str) -> Res<'_, Query> {
+    if input.starts_with('(') {
+        return group(input);
+    }
+    if input.starts_with('"') {
+        return phrase(input).map(|(rest, p)| (rest, Query::Phrase(p)));
+    }
+    match predicate(input) {
+        Err(nom::Err::Error(_)) => bare(input).map(|(rest, w)| (rest, Query::Text(w.to_owned()))),
+        other => other,
+    }
+}
+
+fn unary(input: &str) -> Res<'_, Query> {
+    let Some(rest) = input.strip_prefix('!') else {
+        return atom(input);
+    };
+    match unary(rest) {
+        // a negated word is matched literally: fuzzily, `!db` would hide far too much
+        Ok((rest, Query::Text(word))) => Ok((rest, Query::Not(Box::new(Query::Phrase(word))))),
+        Ok((rest, query)) => Ok((rest, Query::Not(Box::new(query)))),
+        Err(nom::Err::Error(_)) => fail(input, "`!` needs something to negate"),
+        Err(e) => Err(e),
+    }
+}
+
+fn and_expr(input: &str) -> Res<'_, Query> {
+    many1(preceded(ws, unary))
+        .map(|mut parts| match parts.len() {
+            1 => parts.remove(0),
+            _ => Query::And(parts),
+        })
+        .parse(input)
+}
+
+fn or_expr(input: &str) -> Res<'_, Query> {
+    let or = (
+        ws,
+        nom::branch::alt((
+            tag("|"),
+            verify(take_while1(is_word_char), |w: &str| w == "OR"),
+        )),
+        ws,
+    );
+    separated_list1(or, and_expr)
+        .map(|mut parts| match parts.len() {
+            1 => parts.remove(0),
+            _ => Query::Or(parts),
+        })
+        .parse(input)
+}
+
+impl Query {
+    /// Parses `input` once normalised, so words compare with hosts in the
+    /// same form; error offsets are into the normalised text.
+    pub fn parse(input: &str) -> Result<Query, SyntaxError> {
+        let normalized = normalize(input).text;
+        let input = normalized.as_str();
+        let error = |rest: &str, message: String| SyntaxError {
+            offset: input.len() - rest.len(),
+            message,
+        };
+        let (start, _) = ws(input).unwrap_or((input, ""));
+        if start.is_empty() {
+            return Ok(Query::And(Vec::new()));
+        }
+        let (rest, query) = match or_expr(start) {
+            Ok(parsed) => parsed,
+            Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+                let message = e.message.unwrap_or_else(|| unexpected(e.input));
+                return Err(error(e.input, message));
+            }
+            Err(nom::Err::Incomplete(_)) => unreachable!("complete parsers only"),
+        };
+        let rest = rest.trim_start();
+        if rest.is_empty() {
+            Ok(query)
+        } else {
+            Err(error(rest, unexpected(rest)))
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Query::And(parts) if parts.is_empty())
+    }
+
+    /// The parts that must all match: those of an `And`, or the query itself.
+    fn parts(&self) -> &[Query] {
+        match self {
+            Query::And(parts) => parts,
+            query => std::slice::from_ref(query),
+        }
+    }
+
+    /// Whether `self` only adds to `previous`: the same parts with the last
+    /// word typed further and maybe more parts after it. Like fzf, such a
+    /// query is only run against the hosts `previous` matched. That needs
+    /// the longer word to never match a host the shorter one didn't, which
+    /// `scorer` vouches for, see `Scorer::monotonic`; phrases are found
+    /// as they are, so a longer one always does.
+    pub fn narrows(&self, previous: &Query, scorer: &dyn Scorer) -> bool {
+        let (new, old) = (self.parts(), previous.parts());
+        let Some((last, same)) = old.split_last() else {
+            return true;
+        };
+        new.len() >= old.len()
+            && new[..same.len()] == *same
+            && match (last, &new[same.len()]) {
+                (Query::Text(a), Query::Text(b)) => {
+                    a == b || b.starts_with(a.as_str()) && scorer.monotonic(a, b)
+                }
+                (Query::Phrase(a), Query::Phrase(b)) => b.starts_with(a.as_str()),
+                (a, b) => a == b,
+            }
+    }
+
+    /// The fuzzy matches that made `host` pass, or `None` when it doesn't.
+    fn eval(&self, host: &List, keys: &Keys, scorer: &dyn Scorer) -> Option<Vec<HostMatch>> {
+        match self {
+            Query::And(parts) => {
+                let mut found = Vec::new();
+                for part in parts {
+                    found.extend(part.eval(host, keys, scorer)?);
+                }
+                Some(found)
+            }
+            Query::Or(parts) => parts
+                .iter()
+                .filter_map(|part| part.eval(host, keys, scorer))
+                .max_by(|a, b| mean(a).total_cmp(&mean(b))),
+            Query::Not(part) => match part.eval(host, keys, scorer) {
+                Some(_) => None,
+                None => Some(Vec::new()),
+            },
+            Query::Pred(predicate) => predicate.matches(host).then(Vec::new),
+            Query::Text(word) => rank_host(scorer, word, host, keys).map(|m| vec![m]),
+            Query::Phrase(phrase) => phrase_match(phrase, keys).map(|m| vec![m]),
+        }
+    }
+
+    /// Matches `host`, folding the matches of all words into one: the
+    /// score is their mean, the field and positions those of the best.
+    pub fn matches(&self, host: &List, keys: &Keys, scorer: &dyn Scorer) -> Option<HostMatch> {
+        let found = self.eval(host, keys, scorer)?;
+        let Some(best) = found.iter().max_by(|a, b| a.score.total_cmp(&b.score)) else {
+            return Some(HostMatch {
+                score: 0.0,
+                field: Field::Name,
+                text: host.display_name.clone(),
+                positions: Vec::new(),
+            });
+        };
+        let mut positions: Vec<usize> = found
+            .iter()
+            .filter(|m| m.field == best.field && m.text == best.text)
+            .flat_map(|m| m.positions.iter().copied())
+            .collect();
+        positions.sort_unstable();
+        positions.dedup();
+        Some(HostMatch {
+            score: mean(&found),
+            positions,
+            ..best.clone()
+        })
+    }
+}
+
+fn unexpected(rest: &str) -> String {
+    match rest.chars().next() {
+        Some(')') => "unmatched `)`".to_owned(),
+        Some('|') => "`|` needs something on both sides".to_owned(),
+        Some(_) => {
+            let token: String = rest.chars().take_while(|c| !c.is_whitespace()).collect();
+            format!("unexpected `{token}`")
+        }
+        None => "unexpected end of query".to_owned(),
+    }
+}
+
+fn mean(found: &[HostMatch]) -> f64 {
+    if found.is_empty() {
+        0.0
+    } else {
+        found.iter().map(|m| m.score).sum::<f64>() / found.len() as f64
+    }
+}
+
+fn phrase_match(phrase: &str, keys: &Keys) -> Option<HostMatch> {
+    let phrase = fold(phrase).text;
+    let len = phrase.chars().count();
+    keys.iter().find_map(|(field, text, _, folded)| {
+        let at = folded.text.find(&phrase)?;
+        let start = folded.text[..at].chars().count();
+        let positions: Vec<usize> = (start..start + len).collect();
+        Some(HostMatch {
+            score: field.weight(),
+            field,
+            text: text.to_owned(),
+            positions: folded.original_positions(&positions),
+        })
+    })
+}
+
+/// Matches like a shell glob when `pattern` has `*` or `?`; otherwise
+/// `exact` decides between equality and substring. Both are normalised and
+/// case is ignored.
+fn text_matches(pattern: &str, text: &str, exact: bool) -> bool {
+    let fold = |s: &str| normalize(s).folded().text;
+    let (pattern, text) = (fold(pattern), fold(text));
+    if pattern.contains(['*', '?']) {
+        wildcard_match(&pattern, &text)
+    } else if exact {
+        pattern == text
+    } else {
+        text.contains(&pattern)
+    }
+}
+
+fn in_net(addr: IpAddr, net: IpAddr, prefix: u8) -> bool {
+    match (addr, net) {
+        (IpAddr::V4(addr), IpAddr::V4(net)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(addr) & mask == u32::from(net) & mask
+        }
+        (IpAddr::V6(addr), IpAddr::V6(net)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(addr) & mask == u128::from(net) & mask
+        }
+        _ => false,
+    }
+}
+
+impl Predicate {
+    pub fn matches(&self, host: &List) -> bool {
+        match self {
+            Predicate::Tag(tag) => host.tags.iter().any(|t| text_matches(tag, t, true)),
+            Predicate::User(user) => text_matches(user, &host.user, true),
+            Predicate::Host(name) => text_matches(name, &host.hostname, false),
+            Predicate::Alias(alias) => std::iter::once(&host.alias)
+                .chain(&host.aliases)
+                .any(|a| text_matches(alias, a, false)),
+            Predicate::Desc(text) => text_matches(text, &host.description, false),
+            Predicate::Port(port) => host.port.unwrap_or(22) == *port,
+            Predicate::Net(net, prefix) => host
+                .hostname
+                .parse()
+                .is_ok_and(|addr| in_net(addr, *net, *prefix)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scorer::{Fuzzy, JARO_WINKLER};
+
+    fn text(word: &str) -> Query {
+        Query::Text(word.to_owned())
+    }
+
+    fn host(alias: &str, hostname: &str, user: &str, tags: &[&str]) -> List {
+        List {
+            alias: alias.to_owned(),
+            hostname: hostname.to_owned(),
+            display_name: alias.to_owned(),
+            user: user.to_owned(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..List::default()
+        }
+    }
+
+    fn hosts() -> Vec<List> {
+        vec![
+            host("web-prod", "10.20.0.5", "deploy", &["prod", "web"]),
+            host("db-prod", "10.20.1.9", "postgres", &["prod", "db"]),
+            host("web-stage", "10.30.0.5", "deploy", &["staging"]),
+            List {
+                port: Some(2222),
+                ..host("bastion", "bastion.example.com", "root", &[])
+            },
+        ]
+    }
+
+    fn select(query: &str) -> Vec<String> {
+        let query = Query::parse(query).unwrap();
+        hosts()
+            .into_iter()
+            .filter(|h| query.matches(h, &Keys::new(h), &Fuzzy).is_some())
+            .map(|h| h.alias)
+            .collect()
+    }
+
+    #[test]
+    fn parses_structure() {
+        assert_eq!(
+            Query::parse("tag:prod !db web").unwrap(),
+            Query::And(vec![
+                Query::Pred(Predicate::Tag("prod".to_owned())),
+                Query::Not(Box::new(Query::Phrase("db".to_owned()))),
+                text("web"),
+            ])
+        );
+        assert_eq!(
+            Query::parse("a | b OR (c d)").unwrap(),
+            Query::Or(vec![
+                text("a"),
+                text("b"),
+                Query::And(vec![text("c"), text("d")])
+            ])
+        );
+        assert_eq!(
+            Query::parse(r#"desc:"public web" "two words""#).unwrap(),
+            Query::And(vec![
+                Query::Pred(Predicate::Desc("public web".to_owned())),
+                Query::Phrase("two words".to_owned()),
+            ])
+        );
+        assert!(Query::parse("   ").unwrap().is_empty());
+        // ORACLE is a word, and so is an IPv6 address
+        assert_eq!(Query::parse("ORACLE").unwrap(), text("ORACLE"));
+        assert_eq!(Query::parse("fe80::1").unwrap(), text("fe80::1"));
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        let error = |q: &str| Query::parse(q).unwrap_err();
+        assert_eq!(error("tga:prod").message, "unknown field `tga`");
+        assert_eq!(error("web (a b").message, "missing `)`");
+        assert_eq!(error("web (a b").offset, 4);
+        assert_eq!(error("a )").message, "unmatched `)`");
+        assert_eq!(error("port:ssh").message, "`ssh` is not a port number");
+        assert_eq!(error("net:10.0.0.0/33").offset, 4);
+        assert_eq!(error("\"open").message, "unclosed `\"`");
+        assert_eq!(error("a |").message, "`|` needs something on both sides");
+        assert_eq!(error("!").message, "`!` needs something to negate");
+        assert_eq!(error("tag:").message, "`tag:` needs a value");
+    }
+
+    #[test]
+    fn filters_hosts() {
+        assert_eq!(select("tag:prod user:deploy"), ["web-prod"]);
+        assert_eq!(select("tag:prod !db"), ["web-prod"]);
+        assert_eq!(select("port:2222"), ["bastion"]);
+        assert_eq!(select("port:22").len(), 3);
+        assert_eq!(select("net:10.20.0.0/16"), ["web-prod", "db-prod"]);
+        assert_eq!(select("tag:staging | user:root"), ["web-stage", "bastion"]);
+        assert_eq!(select("!(tag:prod | tag:staging)"), ["bastion"]);
+        assert_eq!(select("tag:sta* web"), ["web-stage"]);
+        assert_eq!(select("wbstg"), ["web-stage"]);
+    }
+
+    #[test]
+    fn keeps_positions_of_free_text() {
+        let query = Query::parse("tag:prod web").unwrap();
+        let host = &hosts()[0];
+        let found = query.matches(host, &Keys::new(host), &Fuzzy).unwrap();
+        assert_eq!((found.field, found.positions), (Field::Name, vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn folds_case_in_full() {
+        let host = host("straße-İzmir", "10.0.0.1", "ADMİN", &["GROSS"]);
+        let keys = Keys::new(&host);
+        let found = |query: &str| Query::parse(query).unwrap().matches(&host, &keys, &Fuzzy);
+        let phrase = found(r#""STRASSE-izmir""#).unwrap();
+        assert_eq!(phrase.positions, (0..12).collect::<Vec<_>>());
+        assert!(found("user:admin tag:groß").is_some());
+    }
+
+    #[test]
+    fn growing_queries_narrow() {
+        /// Vouches for every word, leaving only the query's structure.
+        struct Monotonic;
+        impl Scorer for Monotonic {
+            fn name(&self) -> &'static str {
+                "monotonic"
+            }
+            fn score(&self, _: &str, _: &str) -> Option<(f64, Vec<usize>)> {
+                None
+            }
+            fn monotonic(&self, _: &str, _: &str) -> bool {
+                true
+            }
+        }
+        let narrows = |new: &str, old: &str| {
+            Query::parse(new)
+                .unwrap()
+                .narrows(&Query::parse(old).unwrap(), &Monotonic)
+        };
+        assert!(narrows("web", ""));
+        assert!(narrows("webp", "web"));
+        assert!(narrows("web prod", "web"));
+        assert!(narrows("tag:prod web", "tag:prod we"));
+        assert!(narrows("web | db", ""));
+        // more alternatives, a longer negation or a deleted char widen it
+        assert!(!narrows("web | db", "web"));
+        assert!(!narrows("!db", "!d"));
+        assert!(!narrows("we", "web"));
+        assert!(!narrows("tag:prodx", "tag:prod"));
+
+        let with = |scorer: &dyn Scorer, new: &str, old: &str| {
+            Query::parse(new)
+                .unwrap()
+                .narrows(&Query::parse(old).unwrap(), scorer)
+        };
+        // a word long enough for another typo can match what a shorter one
+        // missed; until then growing words narrow
+        assert!(with(&Fuzzy, "we", "w"));
+        assert!(!with(&Fuzzy, "xoo", "xo"));
+        assert!(with(&Fuzzy, "xooz", "xoo"));
+        assert!(with(&Fuzzy, "xooz x", "xooz"));
+        // only the keys that make a segment three letters long start over
+        let typed = "web-prod-0";
+        for n in 2..=typed.len() {
+            let (old, new) = (&typed[..n - 1], &typed[..n]);
+            let grows_typos = matches!(new, "web" | "web-pro");
+            assert_eq!(with(&Fuzzy, new, old), !grows_typos, "{new:?}");
+        }
+        assert!(!with(&Fuzzy, "webprod", "webpro"));
+        assert!(!with(&JARO_WINKLER, "we", "w"));
+        assert!(with(&JARO_WINKLER, "we prod", "we"));
+        // phrases are matched literally, whatever the scorer
+        assert!(with(&JARO_WINKLER, "\"web prod\"", "\"web p\""));
+        assert!(with(&Fuzzy, "\"xooz\"", "\"xoo\""));
+        assert!(!with(&Fuzzy, "\"xoo\"", "\"xooz\""));
+    }
+
+    #[test]
+    fn matches_normalised_text() {
+        let host = List {
+            tags: vec!["Zürich".to_owned()],
+            display_name: "Bücher-Ｗｅｂ０１".to_owned(),
+            ..host("bücher-web01", "bücher.example", "", &[])
+        };
+        let keys = Keys::new(&host);
+        let matches = |query: &str| Query::parse(query).unwrap().matches(&host, &keys, &Fuzzy);
+        for query in [
+            "bucher",
+            "BÜCHER",
+            "bu\u{308}cher",
+            "web01",
+            "ｗｅｂ",
+            "۰۱",
+            "tag:zurich",
+            "host:BÜCHER",
+            "\"CHER-W\"",
+        ] {
+            assert!(matches(query).is_some(), "{query}");
+        }
+        // positions point into the text as written
+        let found = matches("ｗｅｂ").unwrap();
+        assert_eq!((found.field, found.positions), (Field::Name, vec![7, 8, 9]));
+    }
+}
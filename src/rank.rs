@@ -0,0 +1,259 @@
+//! Combines the matchers: fzf-style subsequences for abbreviations, with the
+//! segment ranker as a fallback for reordered segments and typos.
+use crate::list::List;
+use crate::normalize::{Normalized, normalize};
+use crate::scorer::Scorer;
+use crate::{fuzzy, segment};
+use serde::{Deserialize, Serialize};
+
+/// The parts of a host a query is matched against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
+pub enum Field {
+    /// The name shown in the list (`display_name`).
+    Name,
+    Alias,
+    Aliases,
+    HostName,
+    User,
+    Tags,
+    Description,
+}
+
+impl Field {
+    pub const ALL: [Field; 7] = [
+        Field::Name,
+        Field::Alias,
+        Field::Aliases,
+        Field::HostName,
+        Field::User,
+        Field::Tags,
+        Field::Description,
+    ];
+    /// How much a match in this field counts against one in the shown name.
+    pub fn weight(self) -> f64 {
+        match self {
+            Field::Name | Field::Alias => 1.0,
+            Field::Aliases => 0.9,
+            Field::HostName => 0.85,
+            Field::Tags => 0.75,
+            Field::User => 0.7,
+            Field::Description => 0.6,
+        }
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::Name => "name",
+            Field::Alias => "alias",
+            Field::Aliases => "alias",
+            Field::HostName => "hostname",
+            Field::User => "user",
+            Field::Tags => "tag",
+            Field::Description => "description",
+        }
+    }
+    pub fn values(self, host: &List) -> Vec<&str> {
+        match self {
+            Field::Name => vec![&host.display_name],
+            Field::Alias => vec![&host.alias],
+            Field::Aliases => host.aliases.iter().map(String::as_str).collect(),
+            Field::HostName => vec![&host.hostname],
+            Field::User => vec![&host.user],
+            Field::Tags => host.tags.iter().map(String::as_str).collect(),
+            Field::Description => vec![&host.description],
+        }
+    }
+}
+
+/// A host's field values, extracted and normalised once so searching
+/// doesn't do it again on every keystroke.
+#[derive(Debug, Clone, Default)]
+pub struct Keys {
+    /// Every non-empty field value, in `Field::ALL` order.
+    values: Vec<(Field, String)>,
+    normalized: Vec<Normalized>,
+    /// `normalized` case folded, its origins still into the value.
+    folded: Vec<Normalized>,
+}
+
+impl Keys {
+    pub fn new(host: &List) -> Keys {
+        let values: Vec<(Field, String)> = Field::ALL
+            .iter()
+            .flat_map(|&field| {
+                field
+                    .values(host)
+                    .into_iter()
+                    .filter(|text| !text.is_empty())
+                    .map(move |text| (field, text.to_owned()))
+            })
+            .collect();
+        let normalized: Vec<Normalized> = values.iter().map(|(_, text)| normalize(text)).collect();
+        let folded = normalized.iter().map(Normalized::folded).collect();
+        Keys {
+            values,
+            normalized,
+            folded,
+        }
+    }
+    /// Every value with its field, normalised form and that case folded.
+    pub fn iter(&self) -> impl Iterator<Item = (Field, &str, &Normalized, &Normalized)> {
+        self.values
+            .iter()
+            .zip(&self.normalized)
+            .zip(&self.folded)
+            .map(|(((field, text), normalized), folded)| {
+                (*field, text.as_str(), normalized, folded)
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostMatch {
+    pub score: f64,
+    pub field: Field,
+    /// The text of `field` that matched; `positions` index into it.
+    pub text: String,
+    pub positions: Vec<usize>,
+}
+
+/// Matches `query` against every field of a host and keeps the best
+/// weighted match. An empty query matches with no field.
+pub fn rank_host(scorer: &dyn Scorer, query: &str, host: &List, keys: &Keys) -> Option<HostMatch> {
+    if query.trim().is_empty() {
+        return Some(HostMatch {
+            score: 0.0,
+            field: Field::Name,
+            text: host.display_name.clone(),
+            positions: Vec::new(),
+        });
+    }
+    let mut best: Option<(f64, Field, &str, &Normalized, Vec<usize>)> = None;
+    for (field, text, normalized, _) in keys.iter() {
+        let Some((score, positions)) = scorer.score(query, &normalized.text) else {
+            continue;
+        };
+        let score = score * field.weight();
+        // earlier fields win ties, so the shown name is preferred
+        if best.as_ref().is_none_or(|b| score > b.0) {
+            best = Some((score, field, text, normalized, positions));
+        }
+    }
+    best.map(|(score, field, text, normalized, positions)| HostMatch {
+        score,
+        field,
+        text: text.to_owned(),
+        positions: normalized.original_positions(&positions),
+    })
+}
+
+/// Scores `text` against `query`, returning the score and the matched char
+/// positions, or `None` when neither matcher accepts it. Whitespace splits
+/// the query into terms that must all match.
+pub fn rank(query: &str, text: &str) -> Option<(f64, Vec<usize>)> {
+    let terms: Vec<&str> = query.split_whitespace().collect();
+    if terms.is_empty() {
+        return Some((0.0, Vec::new()));
+    }
+    let mut fuzzy_score = 0.0;
+    let mut positions = Vec::new();
+    for term in &terms {
+        let Some(m) = fuzzy::fuzzy_match(term, text) else {
+            positions.clear();
+            break;
+        };
+        fuzzy_score += m.normalized(term.chars().count());
+        positions.extend(m.positions);
+    }
+    let segment_score = segment::score(query, text);
+    if positions.is_empty() {
+        return segment_score.map(|s| (s, Vec::new()));
+    }
+    positions.sort_unstable();
+    positions.dedup();
+    // like the segment ranker, prefer `web` over `webserver`
+    let coverage = positions.len() as f64 / text.chars().count().max(1) as f64;
+    let fuzzy_score = fuzzy_score / terms.len() as f64 * (0.9 + 0.1 * coverage);
+    Some((fuzzy_score.max(segment_score.unwrap_or(0.0)), positions))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scorer::Fuzzy;
+
+    fn best<'a>(query: &str, names: &[&'a str]) -> Option<&'a str> {
+        names
+            .iter()
+            .filter_map(|name| rank(query, name).map(|(s, _)| (s, *name)))
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, name)| name)
+    }
+
+    #[test]
+    fn abbreviation_and_segments() {
+        let names = ["web-prod", "web-staging", "pirouz100", "yooz100"];
+        assert_eq!(best("wbprd", &names), Some("web-prod"));
+        assert_eq!(best("100ooz", &names), Some("yooz100"));
+        assert_eq!(best("zzz", &names), None);
+    }
+
+    #[test]
+    fn reports_positions() {
+        assert_eq!(rank("wbprd", "web-prod").unwrap().1, vec![0, 2, 4, 5, 7]);
+        assert_eq!(
+            rank("prod web", "web-prod").unwrap().1,
+            vec![0, 1, 2, 4, 5, 6, 7]
+        );
+        // typo fallback matches without positions
+        assert_eq!(rank("yoxz100", "yooz100").unwrap().1, Vec::<usize>::new());
+    }
+
+    fn rank_host(query: &str, host: &List) -> Option<HostMatch> {
+        super::rank_host(&Fuzzy, query, host, &Keys::new(host))
+    }
+
+    fn host() -> List {
+        List {
+            alias: "web1".to_owned(),
+            aliases: vec!["www".to_owned()],
+            hostname: "10.20.0.5".to_owned(),
+            display_name: "10.20.0.5".to_owned(),
+            user: "deploy".to_owned(),
+            tags: vec!["prod".to_owned(), "frontend".to_owned()],
+            description: "Public web frontend".to_owned(),
+            ..List::default()
+        }
+    }
+
+    #[test]
+    fn matches_every_field() {
+        let host = host();
+        for (query, field) in [
+            ("10.20", Field::Name),
+            ("web1", Field::Alias),
+            ("www", Field::Aliases),
+            ("deploy", Field::User),
+            ("frontend", Field::Tags),
+            ("public", Field::Description),
+        ] {
+            assert_eq!(rank_host(query, &host).unwrap().field, field, "{query}");
+        }
+        assert!(rank_host("nothing", &host).is_none());
+    }
+
+    #[test]
+    fn weights_favour_the_name() {
+        let mut by_name = host();
+        by_name.display_name = "deploy".to_owned();
+        let by_user = host();
+        assert!(
+            rank_host("deploy", &by_name).unwrap().score
+                > rank_host("deploy", &by_user).unwrap().score
+        );
+    }
+
+    #[test]
+    fn shorter_names_win_ties() {
+        assert_eq!(best("mail", &["mailer100", "mail"]), Some("mail"));
+    }
+}
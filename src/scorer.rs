@@ -0,0 +1,223 @@
+//! The algorithms a search word can be scored with, picked by name from the
+//! config file (`scorer = "..."`) or `--scorer`.
+use crate::normalize::fold;
+use crate::{rank, segment};
+
+pub trait Scorer: Sync {
+    fn name(&self) -> &'static str;
+    /// Scores `text` against one search word: a score in `0.0..=1.0` and the
+    /// matched char positions, or `None` when the text doesn't match at all.
+    fn score(&self, query: &str, text: &str) -> Option<(f64, Vec<usize>)>;
+    /// Whether a text matching `longer` surely matches `shorter`, a prefix
+    /// of it, too, so that `longer` can be run on just the hosts `shorter`
+    /// found.
+    fn monotonic(&self, shorter: &str, longer: &str) -> bool {
+        let _ = (shorter, longer);
+        false
+    }
+}
+
+/// fzf-style subsequences, falling back to segments for typos.
+pub struct Fuzzy;
+
+impl Scorer for Fuzzy {
+    fn name(&self) -> &'static str {
+        "fuzzy"
+    }
+    fn score(&self, query: &str, text: &str) -> Option<(f64, Vec<usize>)> {
+        rank::rank(query, text)
+    }
+    /// Subsequences are monotonic; the segment fallback is too, as long as
+    /// the longer query forgives no more typos.
+    fn monotonic(&self, shorter: &str, longer: &str) -> bool {
+        segment::forgives_no_more(shorter, longer)
+    }
+}
+
+/// Alphabetic and numeric segments matched independently.
+pub struct Segment;
+
+impl Scorer for Segment {
+    fn name(&self) -> &'static str {
+        "segment"
+    }
+    fn score(&self, query: &str, text: &str) -> Option<(f64, Vec<usize>)> {
+        segment::score(query, text).map(|s| (s, Vec::new()))
+    }
+    fn monotonic(&self, shorter: &str, longer: &str) -> bool {
+        segment::forgives_no_more(shorter, longer)
+    }
+}
+
+/// A strsim string similarity. The query is compared with the whole text,
+/// with each of its words and with its prefix of the query's length; the
+/// best of those has to reach `threshold`.
+pub struct Similarity {
+    name: &'static str,
+    metric: fn(&str, &str) -> f64,
+    threshold: f64,
+}
+
+impl Scorer for Similarity {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+    fn score(&self, query: &str, text: &str) -> Option<(f64, Vec<usize>)> {
+        // compared case folded, positions found there map back to `text`
+        let (query, folded) = (fold(query).text, fold(text));
+        let text = folded.text.as_str();
+        let prefix: String = text.chars().take(query.chars().count()).collect();
+        let best = std::iter::once(text)
+            .chain(text.split(|c: char| !c.is_alphanumeric()))
+            .chain(std::iter::once(prefix.as_str()))
+            .filter(|candidate| !candidate.is_empty())
+            .map(|candidate| (self.metric)(&query, candidate))
+            .fold(0.0, f64::max);
+        if best < self.threshold {
+            return None;
+        }
+        let positions = match text.find(&query) {
+            Some(at) => {
+                let start = text[..at].chars().count();
+                let positions: Vec<usize> = (start..start + query.chars().count()).collect();
+                folded.original_positions(&positions)
+            }
+            None => Vec::new(),
+        };
+        Some((best, positions))
+    }
+}
+
+pub const JARO_WINKLER: Similarity = Similarity {
+    name: "jaro-winkler",
+    metric: strsim::jaro_winkler,
+    threshold: 0.8,
+};
+pub const DAMERAU_LEVENSHTEIN: Similarity = Similarity {
+    name: "damerau-levenshtein",
+    metric: strsim::normalized_damerau_levenshtein,
+    threshold: 0.6,
+};
+pub const LEVENSHTEIN: Similarity = Similarity {
+    name: "levenshtein",
+    metric: strsim::normalized_levenshtein,
+    threshold: 0.6,
+};
+pub const SORENSEN_DICE: Similarity = Similarity {
+    name: "sorensen-dice",
+    metric: strsim::sorensen_dice,
+    threshold: 0.5,
+};
+
+/// Every scorer, the default first.
+pub const SCORERS: &[&dyn Scorer] = &[
+    &Fuzzy,
+    &Segment,
+    &JARO_WINKLER,
+    &DAMERAU_LEVENSHTEIN,
+    &LEVENSHTEIN,
+    &SORENSEN_DICE,
+];
+
+pub fn by_name(name: &str) -> Option<&'static dyn Scorer> {
+    SCORERS.iter().copied().find(|s| s.name() == name)
+}
+
+pub fn names() -> Vec<&'static str> {
+    SCORERS.iter().map(|s| s.name()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::prelude::*;
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+
+    fn corpus(name: &str) -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/corpus");
+        fs::read_to_string(path.join(name)).unwrap()
+    }
+
+    fn top(scorer: &dyn Scorer, query: &str, hosts: &[&str], n: usize) -> Vec<String> {
+        let mut ranked: Vec<(f64, &str)> = hosts
+            .iter()
+            .filter_map(|h| scorer.score(query, h).map(|(s, _)| (s, *h)))
+            .collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+        ranked.iter().take(n).map(|(_, h)| h.to_string()).collect()
+    }
+
+    /// Every scorer's top three for every corpus query, side by side in
+    /// `scorers.golden`; run with `GOSSH_BLESS=1` to rewrite it.
+    #[test]
+    fn corpus_rankings() {
+        let hosts = corpus("hosts.txt");
+        let hosts: Vec<&str> = hosts.lines().collect();
+        let queries = corpus("queries.txt");
+        let mut actual = String::new();
+        for query in queries.lines() {
+            actual.push_str(&format!("{query}\n"));
+            for scorer in SCORERS {
+                let top = top(*scorer, query, &hosts, 3).join(" ");
+                let line = format!("  {:<20}{top}", scorer.name());
+                actual.push_str(line.trim_end());
+                actual.push('\n');
+            }
+        }
+        let golden =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/corpus/scorers.golden");
+        if env::var_os("GOSSH_BLESS").is_some() {
+            fs::write(&golden, &actual).unwrap();
+        }
+        assert_eq!(actual, fs::read_to_string(golden).unwrap());
+    }
+
+    #[test]
+    fn default_finds_the_obvious_host() {
+        let hosts = corpus("hosts.txt");
+        let hosts: Vec<&str> = hosts.lines().collect();
+        for (query, expected) in [("100ooz", "yooz100"), ("wbprd01", "web-prod-01")] {
+            assert_eq!(top(SCORERS[0], query, &hosts, 1), [expected]);
+        }
+    }
+
+    #[test]
+    fn similarity_positions_index_the_text() {
+        let (_, positions) = JARO_WINKLER.score("strasse", "Straße").unwrap();
+        assert_eq!(positions, [0, 1, 2, 3, 4, 5]);
+        let (_, positions) = LEVENSHTEIN.score("stanbul", "İstanbul").unwrap();
+        assert_eq!(positions, [1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn names_are_unique() {
+        let mut names = names();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), SCORERS.len());
+        assert_eq!(by_name("jaro-winkler").unwrap().name(), "jaro-winkler");
+        assert!(by_name("nope").is_none());
+    }
+
+    proptest! {
+        #[test]
+        fn monotonic_queries_match_no_new_text(
+            query in "[a-e0-2-]{1,9}",
+            text in "[a-e0-2-]{0,14}",
+        ) {
+            for scorer in [&Fuzzy as &dyn Scorer, &Segment] {
+                if scorer.score(&query, &text).is_none() {
+                    continue;
+                }
+                for (end, _) in query.char_indices() {
+                    let prefix = &query[..end];
+                    if scorer.monotonic(prefix, &query) {
+                        prop_assert!(scorer.score(prefix, &text).is_some(), "{} {:?}", scorer.name(), prefix);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,482 @@
+//! Filtering the host list as the query is typed. Field values are
+//! extracted once, a query that only grows is run against the previous
+//! results instead of every host when the scorer allows it, and big lists
+//! are scored in parallel.
+use crate::finglish;
+use crate::history::History;
+use crate::layout::Layouts;
+use crate::list::List;
+use crate::normalize::normalize;
+use crate::query::{Query, SyntaxError};
+use crate::rank::{Field, HostMatch, Keys};
+use crate::scorer::Scorer;
+use rayon::prelude::*;
+use std::cmp::Ordering;
+
+/// How much connection history can lift a host over a better text match.
+const FRECENCY_WEIGHT: f64 = 0.25;
+/// Hosts scored per thread at least; below that threads cost more than they save.
+const CHUNK: usize = 2048;
+/// Result sets kept to narrow from, across every reading a query is tried as.
+const RECENT: usize = 32;
+/// A match score of the text as typed past which other readings aren't tried.
+const STRONG: f64 = 0.8;
+/// Hosts offered when nothing matches.
+const SUGGESTIONS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    /// Index of the host in the list the `Search` was made for.
+    pub index: usize,
+    /// The match score with the frecency boost added.
+    pub score: f64,
+    pub matched: HostMatch,
+}
+
+pub struct Search<'a> {
+    hosts: &'a [List],
+    scorer: &'a dyn Scorer,
+    keys: Vec<Keys>,
+    boosts: Vec<f64>,
+    /// The latest queries run, under any reading, and the indices of the
+    /// hosts they matched, oldest first.
+    recent: Vec<(Query, Vec<usize>)>,
+}
+
+/// What a query typed in the search box found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Found {
+    pub hits: Vec<Hit>,
+    /// How the query was read instead, when that found a better match than
+    /// the text as typed: the layout it was folded back from (or
+    /// `finglish`) and the resulting text.
+    pub reading: Option<(String, String)>,
+}
+
+impl<'a> Search<'a> {
+    pub fn new(hosts: &'a [List], scorer: &'a dyn Scorer, history: &History, now: u64) -> Self {
+        let (keys, boosts) = hosts
+            .par_iter()
+            .with_min_len(CHUNK)
+            .map(|host| (Keys::new(host), history.boost(&host.alias, now)))
+            .unzip();
+        Search {
+            hosts,
+            scorer,
+            keys,
+            boosts,
+            recent: Vec::new(),
+        }
+    }
+
+    /// The hosts matching `query`, best first. Hosts you connect to often
+    /// and recently get a boost; with an empty query that boost alone
+    /// decides the order, and hosts never used keep config order.
+    pub fn run(&mut self, query: &Query) -> Vec<Hit> {
+        let narrowed = self
+            .recent
+            .iter()
+            .filter(|(last, _)| query.narrows(last, self.scorer))
+            .map(|(_, matched)| matched)
+            .min_by_key(|matched| matched.len());
+        let candidates = match narrowed {
+            Some(matched) => matched.clone(),
+            None => (0..self.hosts.len()).collect(),
+        };
+        let mut hits: Vec<Hit> = candidates
+            .par_iter()
+            .with_min_len(CHUNK)
+            .filter_map(|&index| {
+                let matched = query.matches(&self.hosts[index], &self.keys[index], self.scorer)?;
+                Some(Hit {
+                    index,
+                    score: matched.score + FRECENCY_WEIGHT * self.boosts[index],
+                    matched,
+                })
+            })
+            .collect();
+        hits.par_sort_unstable_by(by_score);
+        self.recent.retain(|(last, _)| last != query);
+        if self.recent.len() == RECENT {
+            self.recent.remove(0);
+        }
+        let matched = hits.iter().map(|hit| hit.index).collect();
+        self.recent.push((query.clone(), matched));
+        hits
+    }
+
+    /// Runs `input` as typed, folded back from each of `layouts` and spelled
+    /// out in Finglish, and keeps whichever finds the best match; the text
+    /// as typed wins ties, so a Latin query is never mangled, and when it
+    /// matches a host well enough the other readings aren't tried at all.
+    /// Each reading narrows from its own last run like a query as typed.
+    /// Only when no version parses is that an error, the one of the text as
+    /// typed.
+    pub fn run_typed(&mut self, input: &str, layouts: &Layouts) -> Result<Found, SyntaxError> {
+        let typed = Query::parse(input);
+        let mut best = typed.as_ref().ok().map(|query| Found {
+            hits: self.run(query),
+            reading: None,
+        });
+        let strong = |found: &mut Found| {
+            found
+                .hits
+                .first()
+                .is_some_and(|hit| hit.matched.score >= STRONG)
+        };
+        if let Some(found) = best.take_if(strong) {
+            return Ok(found);
+        }
+        let readings = layouts.translations(input).into_iter().chain(
+            finglish::romanize(input)
+                .into_iter()
+                .map(|text| ("finglish", text)),
+        );
+        for (name, text) in readings {
+            let Ok(query) = Query::parse(&text) else {
+                continue;
+            };
+            let hits = self.run(&query);
+            if top(&hits) > best.as_ref().map_or(f64::NEG_INFINITY, |b| top(&b.hits)) {
+                best = Some(Found {
+                    hits,
+                    reading: Some((name.to_owned(), text)),
+                });
+            }
+        }
+        best.ok_or_else(|| typed.expect_err("a query that parses is always run"))
+    }
+
+    /// The hosts whose name, aliases or hostname are the fewest edits away
+    /// from `input`, nearest first, for when nothing matches it. Their
+    /// score is how much of the longer text the edits leave alone.
+    pub fn suggest(&self, input: &str) -> Vec<Hit> {
+        let query = normalize(input.trim()).folded().text;
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let names = [Field::Name, Field::Alias, Field::Aliases, Field::HostName];
+        let mut near: Vec<(usize, Hit)> = self
+            .keys
+            .par_iter()
+            .enumerate()
+            .with_min_len(CHUNK)
+            .filter_map(|(index, keys)| {
+                let (distance, field, text, lower) = keys
+                    .iter()
+                    .filter(|(field, ..)| names.contains(field))
+                    .map(|(field, text, _, folded)| {
+                        let lower = folded.text.as_str();
+                        (
+                            strsim::damerau_levenshtein(&query, lower),
+                            field,
+                            text,
+                            lower,
+                        )
+                    })
+                    .min_by_key(|(distance, ..)| *distance)?;
+                let longest = query.chars().count().max(lower.chars().count());
+                let score = 1.0 - distance as f64 / longest as f64;
+                let matched = HostMatch {
+                    score,
+                    field,
+                    text: text.to_owned(),
+                    positions: Vec::new(),
+                };
+                Some((
+                    distance,
+                    Hit {
+                        index,
+                        score,
+                        matched,
+                    },
+                ))
+            })
+            .collect();
+        near.sort_by_key(|(distance, hit)| (*distance, hit.index));
+        near.truncate(SUGGESTIONS);
+        near.into_iter().map(|(_, hit)| hit).collect()
+    }
+}
+
+/// The host `--select-1` connects to without asking: the one whose alias
+/// is exactly `query`, or the only one that matched.
+pub fn select_one(hits: &[Hit], hosts: &[List], query: &str) -> Option<usize> {
+    let query = query.trim();
+    let exact = hits.iter().find(|hit| {
+        let host = &hosts[hit.index];
+        host.alias == query || host.aliases.iter().any(|a| a == query)
+    });
+    match (exact, hits) {
+        (Some(hit), _) | (None, [hit]) => Some(hit.index),
+        _ => None,
+    }
+}
+
+fn top(hits: &[Hit]) -> f64 {
+    hits.first().map_or(f64::NEG_INFINITY, |hit| hit.score)
+}
+
+/// Best first, then config order. A NaN score (a scorer bug, not worth a
+/// panic) sorts last.
+fn by_score(a: &Hit, b: &Hit) -> Ordering {
+    let key = |hit: &Hit| {
+        if hit.score.is_nan() {
+            f64::NEG_INFINITY
+        } else {
+            hit.score
+        }
+    };
+    key(b).total_cmp(&key(a)).then(a.index.cmp(&b.index))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::layout::DEFAULT;
+    use crate::scorer::{Fuzzy, JARO_WINKLER};
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+    fn named(names: &[&str]) -> Vec<List> {
+        names
+            .iter()
+            .map(|name| List {
+                alias: name.to_string(),
+                display_name: name.to_string(),
+                ..List::default()
+            })
+            .collect()
+    }
+
+    fn filter(list: &[List], query: &str, history: &History, now: u64) -> Vec<String> {
+        let hits = Search::new(list, &Fuzzy, history, now).run(&Query::parse(query).unwrap());
+        hits.iter()
+            .map(|hit| list[hit.index].alias.clone())
+            .collect()
+    }
+
+    #[test]
+    fn empty_query_orders_by_frecency() {
+        let list = named(&["a", "b", "c", "d"]);
+        let mut history = History::default();
+        history.add("c", 1000);
+        history.add("c", 900);
+        history.add("b", 1000);
+        assert_eq!(filter(&list, "", &history, 1000), ["c", "b", "a", "d"]);
+    }
+
+    #[test]
+    fn history_breaks_close_matches() {
+        let list = named(&["web-01", "web-02"]);
+        let mut history = History::default();
+        history.add("web-02", 1000);
+        assert_eq!(filter(&list, "web", &history, 1000)[0], "web-02");
+        // but not a clearly better match
+        assert_eq!(filter(&list, "web01", &history, 1000)[0], "web-01");
+    }
+
+    #[test]
+    fn narrowing_agrees_with_starting_over() {
+        let list = named(&[
+            "web-prod",
+            "web-stage",
+            "db-prod",
+            "webmail",
+            "mail",
+            "yooz",
+        ]);
+        let history = History::default();
+        let typed = [
+            "", "w", "we", "web", "web p", "web pr", "we", "web | db", "x", "xo", "xoo", "xooz",
+        ];
+        for scorer in [&Fuzzy as &dyn Scorer, &JARO_WINKLER] {
+            let mut search = Search::new(&list, scorer, &history, 0);
+            for query in typed {
+                let query = Query::parse(query).unwrap();
+                let narrowed = search.run(&query);
+                let fresh = Search::new(&list, scorer, &history, 0).run(&query);
+                assert_eq!(narrowed, fresh, "{} {query:?}", scorer.name());
+            }
+        }
+    }
+
+    /// `Fuzzy`, counting the texts it scores.
+    #[derive(Default)]
+    struct Counting(AtomicUsize);
+
+    impl Scorer for Counting {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+        fn score(&self, query: &str, text: &str) -> Option<(f64, Vec<usize>)> {
+            self.0.fetch_add(1, AtomicOrdering::Relaxed);
+            Fuzzy.score(query, text)
+        }
+        fn monotonic(&self, shorter: &str, longer: &str) -> bool {
+            Fuzzy.monotonic(shorter, longer)
+        }
+    }
+
+    #[test]
+    fn typing_on_scores_only_the_last_hits() {
+        let mut names = vec!["web-prod".to_owned(), "web-stage".to_owned()];
+        names.extend((0..50).map(|i| format!("db-{i}")));
+        let list = named(&names.iter().map(String::as_str).collect::<Vec<_>>());
+        let history = History::default();
+        let counting = Counting::default();
+        let mut search = Search::new(&list, &counting, &history, 0);
+        search.run(&Query::parse("web").unwrap());
+        counting.0.store(0, AtomicOrdering::Relaxed);
+        let hits = search.run(&Query::parse("web-pro").unwrap());
+        assert_eq!(hits.len(), 1);
+        // the two `web` hosts, by name and by alias
+        assert_eq!(counting.0.load(AtomicOrdering::Relaxed), 4);
+    }
+
+    #[test]
+    fn recent_results_are_capped_across_readings() {
+        let list = named(&["web-prod", "db-prod"]);
+        let history = History::default();
+        let mut search = Search::new(&list, &Fuzzy, &history, 0);
+        for i in 0..2 * RECENT {
+            search.run(&Query::parse(&format!("web{i}")).unwrap());
+        }
+        assert_eq!(search.recent.len(), RECENT);
+        // the same query again replaces its entry
+        let db = Query::parse("db").unwrap();
+        search.run(&db);
+        search.run(&db);
+        assert_eq!(search.recent.iter().filter(|(q, _)| *q == db).count(), 1);
+        assert_eq!(search.recent.len(), RECENT);
+    }
+
+    #[test]
+    fn readings_narrow_like_the_typed_query() {
+        let mut names = vec!["web-prod".to_owned()];
+        names.extend((0..50).map(|i| format!("db-{i}")));
+        let list = named(&names.iter().map(String::as_str).collect::<Vec<_>>());
+        let history = History::default();
+        let counting = Counting::default();
+        let mut search = Search::new(&list, &counting, &history, 0);
+        let layouts = Layouts::select(&["persian"], Vec::new()).unwrap();
+        search.run_typed("صثذ", &layouts).unwrap();
+        counting.0.store(0, AtomicOrdering::Relaxed);
+        let found = search.run_typed("صثذ-", &layouts).unwrap();
+        assert_eq!(found.reading.unwrap().1, "web-");
+        // the text as typed matched nothing before, the reading only
+        // `web-prod`, by name and by alias
+        assert_eq!(counting.0.load(AtomicOrdering::Relaxed), 2);
+    }
+
+    #[test]
+    fn a_strong_typed_match_skips_other_readings() {
+        let list = named(&["سرور", "web-prod"]);
+        let history = History::default();
+        let mut search = Search::new(&list, &Fuzzy, &history, 0);
+        let layouts = Layouts::select(DEFAULT, Vec::new()).unwrap();
+        let found = search.run_typed("سرور", &layouts).unwrap();
+        assert_eq!((found.reading, found.hits[0].index), (None, 0));
+        assert_eq!(search.recent.len(), 1);
+    }
+
+    #[test]
+    fn parallel_matches_sequential_order() {
+        let names: Vec<String> = (0..3 * CHUNK).map(|i| format!("host-{i}")).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let list = named(&names);
+        let found = filter(&list, "", &History::default(), 0);
+        assert_eq!(found, names);
+    }
+
+    #[test]
+    fn picks_the_layout_that_matches() {
+        let list = named(&["web-prod", "db-prod", "mail"]);
+        let history = History::default();
+        let mut search = Search::new(&list, &Fuzzy, &history, 0);
+        let layouts = Layouts::select(&["persian", "dvorak"], Vec::new()).unwrap();
+
+        let found = search.run_typed("صثذ", &layouts).unwrap();
+        assert_eq!(
+            found.reading,
+            Some(("persian".to_owned(), "web".to_owned()))
+        );
+        assert_eq!(found.hits[0].index, 0);
+        // dvorak would turn `mail` into `magp`, which finds nothing
+        let found = search.run_typed("mail", &layouts).unwrap();
+        assert_eq!((found.reading, found.hits[0].index), (None, 2));
+        let found = search.run_typed(",.x", &layouts).unwrap();
+        assert_eq!(found.reading.unwrap().0, "dvorak");
+        assert!(search.run_typed("(web", &layouts).is_err());
+    }
+
+    #[test]
+    fn reads_persian_phonetically() {
+        let list = named(&["web-prod", "yooz100", "pirouz100"]);
+        let history = History::default();
+        let mut search = Search::new(&list, &Fuzzy, &history, 0);
+        let layouts = Layouts::select(DEFAULT, Vec::new()).unwrap();
+        for (typed, host) in [("یوز", 1), ("پیروز", 2), ("یوز۱۰۰", 1)] {
+            let found = search.run_typed(typed, &layouts).unwrap();
+            assert_eq!(found.reading.unwrap().0, "finglish", "{typed}");
+            assert_eq!(found.hits[0].index, host, "{typed}");
+        }
+    }
+
+    #[test]
+    fn selects_one() {
+        let list = named(&["web1", "web10", "db"]);
+        let history = History::default();
+        let mut search = Search::new(&list, &Fuzzy, &history, 0);
+        let mut select = |query: &str| {
+            let hits = search.run(&Query::parse(query).unwrap());
+            select_one(&hits, &list, query)
+        };
+        // `web1` also matches `web10`, but it is an alias
+        assert_eq!(select("web1"), Some(0));
+        assert_eq!(select("db"), Some(2));
+        assert_eq!(select("web"), None);
+        assert_eq!(select("nothing"), None);
+    }
+
+    #[test]
+    fn suggests_the_nearest_hosts() {
+        let names: Vec<String> = (0..10).map(|i| format!("db-{i}")).collect();
+        let mut names: Vec<&str> = names.iter().map(String::as_str).collect();
+        names.extend(["web-prod", "mail"]);
+        let mut list = named(&names);
+        list[10].hostname = "frontend.example.com".to_owned();
+        let history = History::default();
+        let search = Search::new(&list, &Fuzzy, &history, 0);
+
+        let suggested = search.suggest("wbe-porb");
+        assert_eq!(suggested.len(), SUGGESTIONS);
+        assert_eq!(suggested[0].index, 10);
+        assert!(suggested[0].score > suggested[1].score);
+        // the hostname counts, and case doesn't
+        let suggested = search.suggest("FRONTEDN.example.com");
+        assert_eq!(suggested[0].index, 10);
+        assert_eq!(suggested[0].matched.field, Field::HostName);
+        assert!(search.suggest("  ").is_empty());
+    }
+
+    struct Broken;
+
+    impl Scorer for Broken {
+        fn name(&self) -> &'static str {
+            "broken"
+        }
+        fn score(&self, query: &str, text: &str) -> Option<(f64, Vec<usize>)> {
+            let score = if text == query { 1.0 } else { f64::NAN };
+            Some((score, Vec::new()))
+        }
+    }
+
+    #[test]
+    fn nan_scores_sort_last() {
+        let list = named(&["a", "b", "c"]);
+        let history = History::default();
+        let hits = Search::new(&list, &Broken, &history, 0).run(&Query::parse("b").unwrap());
+        let order: Vec<usize> = hits.iter().map(|hit| hit.index).collect();
+        assert_eq!(order, [1, 0, 2]);
+    }
+}
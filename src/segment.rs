@@ -0,0 +1,189 @@
+//! Ranks host names by their alphabetic and numeric segments independently,
+//! so `100ooz` finds `yooz100` no matter which part was typed first.
+use crate::normalize::fold_case;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Alpha,
+    Num,
+}
+
+/// Splits `text` into lowercased runs of letters and runs of digits;
+/// everything else only separates segments. Digits are expected folded to
+/// ASCII already, see `normalize`.
+pub fn segments(text: &str) -> Vec<(Kind, String)> {
+    let mut segments: Vec<(Kind, String)> = Vec::new();
+    let mut last: Option<Kind> = None;
+    for c in text.chars() {
+        let kind = if c.is_alphabetic() {
+            Kind::Alpha
+        } else if c.is_ascii_digit() {
+            Kind::Num
+        } else {
+            last = None;
+            continue;
+        };
+        match segments.last_mut() {
+            Some((k, segment)) if last == Some(kind) && *k == kind => segment.extend(fold_case(c)),
+            _ => segments.push((kind, fold_case(c).collect())),
+        }
+        last = Some(kind);
+    }
+    segments
+}
+
+/// Edits a query segment may contain and still match. Numbers get none:
+/// `100` and `200` are different machines, not a typo.
+fn allowed_typos(kind: Kind, len: usize) -> usize {
+    match kind {
+        Kind::Alpha => (len + 1) / 4,
+        Kind::Num => 0,
+    }
+}
+
+/// Whether `longer`, `shorter` typed further, forgives no more typos in
+/// the segment it extends. Then it matches no segment `shorter` missed: a
+/// segment found within so many edits has a prefix found within as many.
+/// Segments it adds only leave fewer candidates.
+pub fn forgives_no_more(shorter: &str, longer: &str) -> bool {
+    let (shorter, longer) = (segments(shorter), segments(longer));
+    let Some((kind, last)) = shorter.last() else {
+        return true;
+    };
+    longer.get(shorter.len() - 1).is_some_and(|(_, extended)| {
+        allowed_typos(*kind, last.chars().count()) == allowed_typos(*kind, extended.chars().count())
+    })
+}
+
+/// How well `query` matches somewhere inside `segment`, in `0.0..=1.0`.
+fn segment_score(kind: Kind, query: &str, segment: &str) -> Option<f64> {
+    if query == segment {
+        return Some(1.0);
+    }
+    if segment.starts_with(query) {
+        return Some(0.98);
+    }
+    if segment.contains(query) {
+        return Some(0.95);
+    }
+    let allowed = allowed_typos(kind, query.chars().count());
+    // every query char the segment lacks costs an edit of its own
+    let missing = query.chars().filter(|&c| !segment.contains(c)).count();
+    if allowed == 0 || missing > allowed {
+        return None;
+    }
+    // best edit distance against any window of about the query's length
+    let chars: Vec<char> = segment.chars().collect();
+    let len = query.chars().count();
+    let mut best = usize::MAX;
+    for width in len.saturating_sub(1).max(1)..=len + 1 {
+        if width > chars.len() {
+            if best == usize::MAX {
+                best = strsim::damerau_levenshtein(query, segment);
+            }
+            break;
+        }
+        for window in chars.windows(width) {
+            let window: String = window.iter().collect();
+            best = best.min(strsim::damerau_levenshtein(query, &window));
+        }
+    }
+    (best <= allowed).then(|| 0.9 * (1.0 - best as f64 / len as f64))
+}
+
+/// Scores `candidate` against `query`, or `None` when some segment of the
+/// query matches nothing of the same kind in the candidate.
+pub fn score(query: &str, candidate: &str) -> Option<f64> {
+    let query = segments(query);
+    if query.is_empty() {
+        return Some(0.0);
+    }
+    let candidate = segments(candidate);
+    let mut total = 0.0;
+    let mut query_len = 0;
+    for (kind, q) in &query {
+        let best = candidate
+            .iter()
+            .filter(|(k, _)| k == kind)
+            .filter_map(|(_, c)| segment_score(*kind, q, c))
+            .fold(None, |best: Option<f64>, s| {
+                Some(best.map_or(s, |b| b.max(s)))
+            })?;
+        let len = q.chars().count();
+        total += best * len as f64;
+        query_len += len;
+    }
+    let candidate_len: usize = candidate.iter().map(|(_, c)| c.chars().count()).sum();
+    // prefer candidates the query covers more of: `web` over `webserver`
+    let coverage = (query_len as f64 / candidate_len.max(1) as f64).min(1.0);
+    Some(total / query_len as f64 * (0.9 + 0.1 * coverage))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CORPUS: &[&str] = &[
+        "pirouz100",
+        "yooz100",
+        "yooz200",
+        "yooz",
+        "web-prod-01",
+        "web-prod-02",
+        "web-staging-01",
+        "db-prod-01",
+        "10.0.0.5",
+        "mail",
+        "mailer100",
+    ];
+
+    fn rank(query: &str) -> Vec<&'static str> {
+        let mut ranked: Vec<(f64, &str)> = CORPUS
+            .iter()
+            .filter_map(|name| score(query, name).map(|s| (s, *name)))
+            .collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+        ranked.into_iter().map(|(_, name)| name).collect()
+    }
+
+    #[test]
+    fn splits_segments() {
+        assert_eq!(
+            segments("Web-prod02"),
+            vec![
+                (Kind::Alpha, "web".to_owned()),
+                (Kind::Alpha, "prod".to_owned()),
+                (Kind::Num, "02".to_owned()),
+            ]
+        );
+        assert_eq!(segments("100ooz")[0], (Kind::Num, "100".to_owned()));
+    }
+
+    #[test]
+    fn number_first_finds_yooz() {
+        // the example from the old TODO in main.rs
+        assert_eq!(rank("100ooz")[0], "yooz100");
+        assert_eq!(rank("ooz100")[0], "yooz100");
+        assert!(!rank("100ooz").contains(&"yooz200"));
+    }
+
+    #[test]
+    fn segment_order_does_not_matter() {
+        assert_eq!(rank("01 prod web")[0], "web-prod-01");
+        assert_eq!(rank("02web")[0], "web-prod-02");
+    }
+
+    #[test]
+    fn tolerates_small_typos() {
+        assert_eq!(rank("yoz100")[0], "yooz100");
+        assert_eq!(rank("piruz")[0], "pirouz100");
+        assert_eq!(rank("stagign")[0], "web-staging-01");
+        assert!(rank("xyz").is_empty());
+    }
+
+    #[test]
+    fn exact_beats_longer() {
+        assert_eq!(rank("mail")[0], "mail");
+        assert_eq!(rank("yooz")[0], "yooz");
+    }
+}
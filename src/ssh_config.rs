@@ -0,0 +1,267 @@
+//! Lossless ssh_config syntax tree: every byte of the input is kept, so a
+//! file that is parsed and written back without edits comes out unchanged.
+use nom::IResult;
+use nom::Parser;
+use nom::bytes::complete::{take_till, take_while};
+use nom::character::complete::char;
+use nom::combinator::{opt, recognize};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    pub lines: Vec<Line>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    /// Spaces and tabs before the content (all of them, for blank lines).
+    pub indent: String,
+    pub body: Body,
+    /// Spaces and tabs after the content.
+    pub trailing: String,
+    pub eol: Eol,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Body {
+    Empty,
+    /// The comment text, starting with `#`.
+    Comment(String),
+    Directive {
+        key: String,
+        /// Whatever separated key and value: blanks, an `=` or both.
+        sep: String,
+        value: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Eol {
+    /// Only valid on the last line of a file.
+    None,
+    Lf,
+    CrLf,
+}
+
+fn is_blank(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+fn directive(input: &str) -> IResult<&str, (&str, &str)> {
+    (
+        take_till(|c: char| is_blank(c) || c == '='),
+        recognize((take_while(is_blank), opt(char('=')), take_while(is_blank))),
+    )
+        .parse(input)
+}
+
+impl Line {
+    /// Parses a single line without its line terminator.
+    pub fn parse(text: &str, eol: Eol) -> Line {
+        let content = text.trim_start_matches(is_blank);
+        let indent = text[..text.len() - content.len()].to_owned();
+        let content_end = content.trim_end_matches(is_blank).len();
+        let (content, trailing) = content.split_at(content_end);
+        let body = if content.is_empty() {
+            Body::Empty
+        } else if content.starts_with('#') {
+            Body::Comment(content.to_owned())
+        } else {
+            // every piece of `directive` accepts empty input, so this can't fail
+            let (value, (key, sep)) = directive(content).expect("directive keyword");
+            Body::Directive {
+                key: key.to_owned(),
+                sep: sep.to_owned(),
+                value: value.to_owned(),
+            }
+        };
+        Line {
+            indent,
+            body,
+            trailing: trailing.to_owned(),
+            eol,
+        }
+    }
+    /// A new directive line in the usual `    Key value` layout.
+    pub fn directive(indent: &str, key: &str, value: &str) -> Line {
+        Line {
+            indent: indent.to_owned(),
+            body: Body::Directive {
+                key: key.to_owned(),
+                sep: " ".to_owned(),
+                value: value.to_owned(),
+            },
+            trailing: String::new(),
+            eol: Eol::Lf,
+        }
+    }
+    pub fn key_value(&self) -> Option<(&str, &str)> {
+        match &self.body {
+            Body::Directive { key, value, .. } => Some((key, value)),
+            _ => None,
+        }
+    }
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Config {
+        let lines = text
+            .split_inclusive('\n')
+            .map(|line| {
+                if let Some(line) = line.strip_suffix("\r\n") {
+                    Line::parse(line, Eol::CrLf)
+                } else if let Some(line) = line.strip_suffix('\n') {
+                    Line::parse(line, Eol::Lf)
+                } else {
+                    Line::parse(line, Eol::None)
+                }
+            })
+            .collect();
+        Config { lines }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.indent)?;
+        match &self.body {
+            Body::Empty => {}
+            Body::Comment(text) => f.write_str(text)?,
+            Body::Directive { key, sep, value } => write!(f, "{key}{sep}{value}")?,
+        }
+        f.write_str(&self.trailing)?;
+        f.write_str(match self.eol {
+            Eol::None => "",
+            Eol::Lf => "\n",
+            Eol::CrLf => "\r\n",
+        })
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.lines.iter().try_for_each(|line| write!(f, "{line}"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::list::Server;
+    use proptest::prelude::*;
+    use std::path::Path;
+
+    fn blanks() -> impl Strategy<Value = String> {
+        "[ \t]{0,3}"
+    }
+
+    /// Lines that can come out of `Line::parse`; anything else (say a blank
+    /// line with trailing blanks) has no text form of its own.
+    fn line() -> impl Strategy<Value = Line> {
+        let eol = prop_oneof![Just(Eol::Lf), Just(Eol::CrLf)];
+        let empty = blanks().prop_map(|indent| (indent, Body::Empty, String::new()));
+        let comment =
+            (blanks(), "#[^\r\n]{0,20}", blanks()).prop_map(|(indent, text, trailing)| {
+                let text = text.trim_end_matches(is_blank).to_owned();
+                (indent, Body::Comment(text), trailing)
+            });
+        let sep = prop_oneof![
+            Just(" "),
+            Just("\t"),
+            Just("="),
+            Just(" = "),
+            Just("  "),
+            Just(" ="),
+            Just("=\t"),
+        ];
+        let directive = (
+            blanks(),
+            "[^ \t\r\n=#][^ \t\r\n=]{0,12}",
+            sep,
+            "[^ \t\r\n][^\r\n]{0,20}",
+            blanks(),
+        )
+            // an `=` after blanks alone would be read as the separator
+            .prop_filter("separator", |(_, _, sep, value, _)| {
+                sep.contains('=') || !value.starts_with('=')
+            })
+            .prop_map(|(indent, key, sep, value, trailing)| {
+                let value = value.trim_end_matches(is_blank).to_owned();
+                let body = Body::Directive {
+                    key,
+                    sep: sep.to_owned(),
+                    value,
+                };
+                (indent, body, trailing)
+            });
+        (prop_oneof![empty, comment, directive], eol).prop_map(|((indent, body, trailing), eol)| {
+            Line {
+                indent,
+                body,
+                trailing,
+                eol,
+            }
+        })
+    }
+
+    fn config() -> impl Strategy<Value = Config> {
+        (prop::collection::vec(line(), 0..20), any::<bool>()).prop_map(|(mut lines, open)| {
+            // only a non-empty last line can go without a terminator
+            if let Some(last) = lines.last_mut()
+                && open
+                && !last.to_string().trim_end_matches(['\r', '\n']).is_empty()
+            {
+                last.eol = Eol::None;
+            }
+            Config { lines }
+        })
+    }
+
+    /// Text that looks enough like a config to reach the interesting paths.
+    fn config_text() -> impl Strategy<Value = String> {
+        "((Host|HostName|Include|Match|#|=| |\t|\r|\n|\"|\\*|!|[a-z0-9.]{1,6}))*"
+    }
+
+    proptest! {
+        #[test]
+        fn parse_never_panics(text in any::<String>()) {
+            let _ = Config::parse(&text);
+            let _ = Server::from_reader(text.as_bytes(), Path::new("/nonexistent"));
+        }
+
+        #[test]
+        fn configish_text_never_panics(text in config_text()) {
+            let _ = Server::from_reader(text.as_bytes(), Path::new("/nonexistent"));
+        }
+
+        #[test]
+        fn write_then_parse_is_identity(ast in config()) {
+            prop_assert_eq!(Config::parse(&ast.to_string()), ast);
+        }
+
+        #[test]
+        fn unedited_text_round_trips(text in prop_oneof![any::<String>(), config_text()]) {
+            prop_assert_eq!(Config::parse(&text).to_string(), text);
+        }
+    }
+
+    #[test]
+    fn keeps_layout() {
+        let text = "Host web\r\n\tHostName = web.example.com  \n  # note\n   \nUser x";
+        let config = Config::parse(text);
+        assert_eq!(config.lines.len(), 5);
+        assert_eq!(
+            config.lines[1].key_value(),
+            Some(("HostName", "web.example.com"))
+        );
+        assert_eq!(config.lines[4].eol, Eol::None);
+        assert_eq!(config.to_string(), text);
+    }
+
+    #[test]
+    fn lone_separator_round_trips() {
+        let config = Config::parse("=");
+        assert_eq!(config.lines[0].key_value(), Some(("", "")));
+        assert_eq!(config.to_string(), "=");
+    }
+}
@@ -0,0 +1,379 @@
+//! The columns of the host table: what each one shows, how it sorts and
+//! how wide it gets when the terminal is too narrow for all of them.
+use crate::history::History;
+use crate::list::List;
+use crate::rank::{Field, HostMatch};
+use ratatui::style::Style;
+use ratatui::text::{Line, Span};
+use std::cmp::Reverse;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Columns shown when the config doesn't pick any.
+pub const DEFAULT: &[Column] = &[
+    Column::Name,
+    Column::Alias,
+    Column::HostName,
+    Column::User,
+    Column::LastConnected,
+];
+/// No column is laid out wider than this, however long its values.
+const MAX_WIDTH: u16 = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    /// The name shown for the host (`display_name`).
+    Name,
+    Alias,
+    HostName,
+    User,
+    Port,
+    Tags,
+    /// The file and line of the `Host` line.
+    Source,
+    LastConnected,
+}
+
+/// A host's place in the order of one column, worked out once per host
+/// rather than on every comparison. Keys of a column are all one variant
+/// or `Missing`, which sorts after them.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SortKey {
+    Port(u16),
+    Text(String),
+    Source(PathBuf, usize),
+    Connected(Reverse<u64>),
+    Missing,
+}
+
+impl Column {
+    pub const ALL: [Column; 8] = [
+        Column::Name,
+        Column::Alias,
+        Column::HostName,
+        Column::User,
+        Column::Port,
+        Column::Tags,
+        Column::Source,
+        Column::LastConnected,
+    ];
+    /// What the column is called in the config and its header.
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Name => "name",
+            Column::Alias => "alias",
+            Column::HostName => "hostname",
+            Column::User => "user",
+            Column::Port => "port",
+            Column::Tags => "tags",
+            Column::Source => "source",
+            Column::LastConnected => "last-connected",
+        }
+    }
+    pub fn by_name(name: &str) -> Option<Column> {
+        Column::ALL.into_iter().find(|c| c.name() == name)
+    }
+    /// The field a query matched that this column shows, if any.
+    pub fn field(self) -> Option<Field> {
+        match self {
+            Column::Name => Some(Field::Name),
+            Column::Alias => Some(Field::Alias),
+            Column::HostName => Some(Field::HostName),
+            Column::User => Some(Field::User),
+            Column::Tags => Some(Field::Tags),
+            Column::Port | Column::Source | Column::LastConnected => None,
+        }
+    }
+    pub fn cell(self, host: &List, history: &History, now: u64) -> String {
+        match self {
+            Column::Name => host.display_name.clone(),
+            Column::Alias => host.alias.clone(),
+            Column::HostName => host.hostname.clone(),
+            Column::User => host.user.clone(),
+            Column::Port => host.port.map(|p| p.to_string()).unwrap_or_default(),
+            Column::Tags => host.tags.join(", "),
+            Column::Source => match &host.source {
+                Some((path, line)) => format!("{}:{line}", shorten(path)),
+                None => String::new(),
+            },
+            Column::LastConnected => history
+                .last(&host.alias)
+                .map(|time| ago(now.saturating_sub(time)))
+                .unwrap_or_default(),
+        }
+    }
+    /// Where `host` sorts by this column: text alphabetically, ports by
+    /// number and connections newest first. Hosts without a value go last.
+    pub fn sort_key(self, host: &List, history: &History) -> SortKey {
+        let key = match self {
+            Column::Port => host.port.map(SortKey::Port),
+            Column::Source => host
+                .source
+                .clone()
+                .map(|(path, line)| SortKey::Source(path, line)),
+            Column::LastConnected => history
+                .last(&host.alias)
+                .map(|time| SortKey::Connected(Reverse(time))),
+            _ => Some(self.cell(host, history, 0))
+                .filter(|text| !text.is_empty())
+                .map(|text| SortKey::Text(text.to_lowercase())),
+        };
+        key.unwrap_or(SortKey::Missing)
+    }
+    /// Where in this column's cell for `host` the chars `matched` found
+    /// are, when the cell shows the text they were found in.
+    pub fn positions(self, host: &List, matched: &HostMatch) -> Vec<usize> {
+        if self.field() != Some(matched.field) {
+            return Vec::new();
+        }
+        let offset = match self {
+            // the matched tag is one of those joined in the cell
+            Column::Tags => {
+                let Some(at) = host.tags.iter().position(|tag| *tag == matched.text) else {
+                    return Vec::new();
+                };
+                host.tags[..at]
+                    .iter()
+                    .map(|tag| tag.chars().count() + 2)
+                    .sum()
+            }
+            _ if self.cell(host, &History::default(), 0) == matched.text => 0,
+            _ => return Vec::new(),
+        };
+        matched.positions.iter().map(|p| p + offset).collect()
+    }
+    /// The width the column's longest value and its header need, capped.
+    pub fn natural_width(self, hosts: &[List], history: &History, now: u64) -> u16 {
+        let longest = hosts
+            .iter()
+            .map(|host| self.cell(host, history, now).chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(self.name().chars().count() + 2);
+        longest.min(MAX_WIDTH as usize) as u16
+    }
+}
+
+/// `text` with the chars at `positions` in `matched` style, as fzf shows
+/// what a query matched.
+pub fn highlight(text: &str, positions: &[usize], matched: Style) -> Line<'static> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut run = String::new();
+    let mut in_match = false;
+    for (i, c) in text.chars().enumerate() {
+        let is_match = positions.binary_search(&i).is_ok();
+        if is_match != in_match && !run.is_empty() {
+            let run = std::mem::take(&mut run);
+            spans.push(if in_match {
+                Span::styled(run, matched)
+            } else {
+                Span::raw(run)
+            });
+        }
+        in_match = is_match;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(if in_match {
+            Span::styled(run, matched)
+        } else {
+            Span::raw(run)
+        });
+    }
+    Line::from(spans)
+}
+
+/// `path` with the home directory written as `~`.
+pub fn shorten(path: &Path) -> String {
+    let home = env::var_os("HOME").filter(|home| !home.is_empty());
+    match home.and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// How long ago something `secs` seconds old was, roughly.
+pub fn ago(secs: u64) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+    match secs {
+        s if s < MINUTE => "just now".to_owned(),
+        s if s < HOUR => format!("{}m ago", s / MINUTE),
+        s if s < DAY => format!("{}h ago", s / HOUR),
+        s if s < 14 * DAY => format!("{}d ago", s / DAY),
+        s if s < 60 * DAY => format!("{}w ago", s / (7 * DAY)),
+        s if s < 365 * DAY => format!("{}mo ago", s / (30 * DAY)),
+        s => format!("{}y ago", s / (365 * DAY)),
+    }
+}
+
+/// Shows `column` if it's hidden and hides it if not, but never the last
+/// one. A column shown again goes back where `Column::ALL` puts it among
+/// the others.
+pub fn toggle(columns: &mut Vec<Column>, column: Column) {
+    if let Some(at) = columns.iter().position(|&c| c == column) {
+        if columns.len() > 1 {
+            columns.remove(at);
+        }
+        return;
+    }
+    let rank = |c: &Column| Column::ALL.iter().position(|a| a == c);
+    let at = columns
+        .iter()
+        .position(|c| rank(c) > rank(&column))
+        .unwrap_or(columns.len());
+    columns.insert(at, column);
+}
+
+/// The sort after `sort`: relevance (`None`), then each shown column in
+/// turn, then relevance again.
+pub fn next_sort(sort: Option<Column>, columns: &[Column]) -> Option<Column> {
+    match sort.and_then(|sort| columns.iter().position(|&c| c == sort)) {
+        Some(at) => columns.get(at + 1).copied(),
+        None => columns.first().copied(),
+    }
+}
+
+/// Fits columns of `natural` widths into `available` cells. When they
+/// don't fit, the widest ones are cut down to a common width, so short
+/// columns like ports stay whole.
+pub fn widths(natural: &[u16], available: u16) -> Vec<u16> {
+    let total = |cap: u16| natural.iter().map(|&n| n.min(cap) as u32).sum::<u32>();
+    let widest = natural.iter().copied().max().unwrap_or(0);
+    if total(widest) <= available as u32 {
+        return natural.to_vec();
+    }
+    // the widest cap that fits; total(0) is 0, so there is one
+    let (mut low, mut high) = (0, widest);
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if total(mid) <= available as u32 {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    let mut spare = available as u32 - total(low);
+    natural
+        .iter()
+        .map(|&n| {
+            if n > low && spare > 0 {
+                spare -= 1;
+                low + 1
+            } else {
+                n.min(low)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn host(alias: &str, port: Option<u16>) -> List {
+        List {
+            alias: alias.to_owned(),
+            port,
+            ..List::default()
+        }
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for column in Column::ALL {
+            assert_eq!(Column::by_name(column.name()), Some(column));
+        }
+        assert_eq!(Column::by_name("colour"), None);
+    }
+
+    #[test]
+    fn sorts_with_missing_values_last() {
+        let (a, b, c) = (host("b", Some(22)), host("A", None), host("c", Some(2222)));
+        let mut history = History::default();
+        history.add("c", 100);
+        history.add("b", 50);
+        let sorted = |column: Column| {
+            let mut hosts = [&a, &b, &c];
+            hosts.sort_by_cached_key(|host| column.sort_key(host, &history));
+            hosts.iter().map(|h| h.alias.as_str()).collect::<Vec<_>>()
+        };
+        assert_eq!(sorted(Column::Alias), ["A", "b", "c"]);
+        assert_eq!(sorted(Column::Port), ["b", "c", "A"]);
+        assert_eq!(sorted(Column::LastConnected), ["c", "b", "A"]);
+    }
+
+    #[test]
+    fn toggles_and_cycles() {
+        let mut columns = vec![Column::Alias, Column::User];
+        toggle(&mut columns, Column::HostName);
+        assert_eq!(columns, [Column::Alias, Column::HostName, Column::User]);
+        toggle(&mut columns, Column::Alias);
+        toggle(&mut columns, Column::User);
+        toggle(&mut columns, Column::HostName);
+        assert_eq!(columns, [Column::HostName]);
+
+        let columns = [Column::Alias, Column::Port];
+        let mut sort = None;
+        let mut seen = Vec::new();
+        for _ in 0..3 {
+            sort = next_sort(sort, &columns);
+            seen.push(sort);
+        }
+        assert_eq!(seen, [Some(Column::Alias), Some(Column::Port), None]);
+    }
+
+    #[test]
+    fn widest_columns_shrink_first() {
+        assert_eq!(widths(&[5, 20, 10], 40), [5, 20, 10]);
+        assert_eq!(widths(&[5, 20, 10], 25), [5, 10, 10]);
+        assert_eq!(widths(&[5, 20, 10], 22), [5, 9, 8]);
+        assert_eq!(widths(&[5, 20], 0), [0, 0]);
+    }
+
+    #[test]
+    fn positions_land_in_the_matching_cell() {
+        let host = List {
+            alias: "web1".to_owned(),
+            display_name: "web1".to_owned(),
+            tags: vec!["prod".to_owned(), "frontend".to_owned()],
+            ..List::default()
+        };
+        let matched = |field, text: &str, positions: Vec<usize>| HostMatch {
+            score: 1.0,
+            field,
+            text: text.to_owned(),
+            positions,
+        };
+        let tag = matched(Field::Tags, "frontend", vec![0, 1]);
+        assert_eq!(Column::Tags.positions(&host, &tag), [6, 7]);
+        assert!(Column::Alias.positions(&host, &tag).is_empty());
+        let name = matched(Field::Name, "web1", vec![0, 3]);
+        assert_eq!(Column::Name.positions(&host, &name), [0, 3]);
+    }
+
+    #[test]
+    fn highlights_runs() {
+        let bold = Style::default().add_modifier(ratatui::style::Modifier::BOLD);
+        let line = highlight("web-prod", &[0, 1, 4], bold);
+        let spans: Vec<(&str, bool)> = line
+            .spans
+            .iter()
+            .map(|s| (s.content.as_ref(), s.style == bold))
+            .collect();
+        assert_eq!(
+            spans,
+            [("we", true), ("b-", false), ("p", true), ("rod", false)]
+        );
+        assert_eq!(highlight("", &[], bold).spans.len(), 0);
+    }
+
+    #[test]
+    fn ages() {
+        assert_eq!(ago(5), "just now");
+        assert_eq!(ago(3 * 3600), "3h ago");
+        assert_eq!(ago(20 * 24 * 3600), "2w ago");
+        assert_eq!(ago(800 * 24 * 3600), "2y ago");
+    }
+}
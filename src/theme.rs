@@ -0,0 +1,315 @@
+//! Colours of the picker. Themes are given in 24-bit colour and brought
+//! down to what the terminal shows: 256 or 16 colours, or none at all
+//! when `NO_COLOR` is set.
+use ratatui::style::{Color, Modifier, Style};
+use std::env;
+
+/// The theme used when the config doesn't name one.
+pub const DEFAULT: &str = "dark";
+/// How far each row further from the selection fades towards the
+/// background, up to `FADE_ROWS` rows.
+const FADE_STEP: f64 = 0.08;
+const FADE_ROWS: usize = 4;
+
+pub type Rgb = (u8, u8, u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Depth {
+    /// `NO_COLOR`: styles only, like bold and underline.
+    None,
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl Depth {
+    pub fn detect() -> Depth {
+        let var = |name| env::var(name).ok();
+        Depth::from_env(
+            var("NO_COLOR").as_deref(),
+            var("COLORTERM").as_deref(),
+            var("TERM").as_deref(),
+        )
+    }
+    /// The depth the values of `NO_COLOR`, `COLORTERM` and `TERM` say the
+    /// terminal has. `NO_COLOR` counts when set to anything but empty.
+    pub fn from_env(no_color: Option<&str>, colorterm: Option<&str>, term: Option<&str>) -> Depth {
+        if no_color.is_some_and(|v| !v.is_empty()) {
+            Depth::None
+        } else if matches!(colorterm, Some("truecolor" | "24bit")) {
+            Depth::TrueColor
+        } else if term.is_some_and(|t| t.contains("256color")) {
+            Depth::Ansi256
+        } else {
+            Depth::Ansi16
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    /// The rows' text; the further from the selection, the more it fades
+    /// towards `background`.
+    pub text: Rgb,
+    pub background: Rgb,
+    pub selected: Rgb,
+    /// Characters the query matched.
+    pub accent: Rgb,
+    /// The search box title when the query was read another way.
+    pub reading: Rgb,
+    pub error: Rgb,
+    /// The title of the list when it shows suggestions.
+    pub suggestion: Rgb,
+    /// Hosts marked for a batch action.
+    pub marked: Rgb,
+    pub depth: Depth,
+}
+
+pub fn builtin() -> Vec<Theme> {
+    vec![
+        Theme {
+            name: "dark".to_owned(),
+            text: (255, 255, 255),
+            background: (0, 0, 0),
+            selected: (255, 215, 0),
+            accent: (95, 215, 255),
+            reading: (0, 215, 215),
+            error: (255, 85, 85),
+            suggestion: (255, 215, 0),
+            marked: (135, 215, 95),
+            depth: Depth::TrueColor,
+        },
+        Theme {
+            name: "light".to_owned(),
+            text: (28, 28, 28),
+            background: (255, 255, 255),
+            selected: (175, 95, 0),
+            accent: (0, 95, 215),
+            reading: (0, 135, 135),
+            error: (215, 0, 0),
+            suggestion: (175, 95, 0),
+            marked: (0, 135, 0),
+            depth: Depth::TrueColor,
+        },
+    ]
+}
+
+/// Reads `#rrggbb`.
+pub fn parse_rgb(text: &str) -> Result<Rgb, String> {
+    let hex = text
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6 && hex.is_ascii())
+        .ok_or_else(|| format!("colour {text:?} isn't of the form #rrggbb"))?;
+    let channel = |at: usize| {
+        u8::from_str_radix(&hex[at..at + 2], 16)
+            .map_err(|_| format!("colour {text:?} isn't of the form #rrggbb"))
+    };
+    Ok((channel(0)?, channel(2)?, channel(4)?))
+}
+
+fn fade((r, g, b): Rgb, (br, bg, bb): Rgb, amount: f64) -> Rgb {
+    let mix = |c: u8, b: u8| (c as f64 + (b as f64 - c as f64) * amount).round() as u8;
+    (mix(r, br), mix(g, bg), mix(b, bb))
+}
+
+/// The 16 ANSI colours as xterm shows them.
+const ANSI16: [(Color, Rgb); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn distance((r, g, b): Rgb, (r2, g2, b2): Rgb) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(r, r2) + d(g, g2) + d(b, b2)
+}
+
+/// The nearest colour of the 256-colour palette's cube and grey ramp.
+fn ansi256(rgb: Rgb) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let level = |c: u8| {
+        (0..6)
+            .min_by_key(|&i| (LEVELS[i] as i32 - c as i32).abs())
+            .unwrap()
+    };
+    let (r, g, b) = (level(rgb.0), level(rgb.1), level(rgb.2));
+    let cube = (LEVELS[r], LEVELS[g], LEVELS[b]);
+    let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let grey = ((average.saturating_sub(8) + 5) / 10).min(23) as u8;
+    let grey_level = 8 + 10 * grey;
+    if distance(rgb, (grey_level, grey_level, grey_level)) < distance(rgb, cube) {
+        232 + grey
+    } else {
+        16 + 36 * r as u8 + 6 * g as u8 + b as u8
+    }
+}
+
+impl Theme {
+    /// `rgb` as the terminal can show it, or `None` without colours.
+    pub fn color(&self, rgb: Rgb) -> Option<Color> {
+        match self.depth {
+            Depth::None => None,
+            Depth::Ansi16 => ANSI16
+                .iter()
+                .min_by_key(|(_, ansi)| distance(rgb, *ansi))
+                .map(|(color, _)| *color),
+            Depth::Ansi256 => Some(Color::Indexed(ansi256(rgb))),
+            Depth::TrueColor => Some(Color::Rgb(rgb.0, rgb.1, rgb.2)),
+        }
+    }
+    fn fg(&self, rgb: Rgb) -> Style {
+        match self.color(rgb) {
+            Some(color) => Style::default().fg(color),
+            None => Style::default(),
+        }
+    }
+    /// The row `distance` rows from the selected one, if any is.
+    pub fn row(&self, distance: Option<usize>) -> Style {
+        match distance {
+            None => self.fg(self.text),
+            Some(0) if self.depth == Depth::None => Style::default().add_modifier(Modifier::BOLD),
+            Some(0) => self.fg(self.selected),
+            // 16 colours are too few to fade through
+            Some(d) if self.depth < Depth::Ansi256 && d >= 2 => {
+                self.fg(self.text).add_modifier(Modifier::DIM)
+            }
+            Some(d) => self.fg(self.faded(self.text, d)),
+        }
+    }
+    /// A marked row `distance` rows from the selected one. Without colours
+    /// it is reversed, bold being the selection.
+    pub fn marked(&self, distance: Option<usize>) -> Style {
+        if self.depth == Depth::None {
+            return Style::default().add_modifier(Modifier::REVERSED);
+        }
+        let marked = match distance {
+            Some(d) if self.depth >= Depth::Ansi256 => self.faded(self.marked, d),
+            _ => self.marked,
+        };
+        self.fg(marked).add_modifier(Modifier::BOLD)
+    }
+    /// The matched characters of that row, fading along with it.
+    pub fn matched(&self, distance: Option<usize>) -> Style {
+        let accent = match distance {
+            Some(d) if self.depth >= Depth::Ansi256 => self.faded(self.accent, d),
+            _ => self.accent,
+        };
+        let style = self.fg(accent).add_modifier(Modifier::BOLD);
+        if self.depth == Depth::None {
+            style.add_modifier(Modifier::UNDERLINED)
+        } else {
+            style
+        }
+    }
+    fn faded(&self, rgb: Rgb, distance: usize) -> Rgb {
+        let amount = distance.min(FADE_ROWS) as f64 * FADE_STEP;
+        fade(rgb, self.background, amount)
+    }
+    pub fn reading(&self) -> Style {
+        self.fg(self.reading)
+    }
+    pub fn error(&self) -> Style {
+        self.fg(self.error)
+    }
+    pub fn suggestion(&self) -> Style {
+        self.fg(self.suggestion)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dark(depth: Depth) -> Theme {
+        Theme {
+            depth,
+            ..builtin().remove(0)
+        }
+    }
+
+    #[test]
+    fn detects_depth() {
+        assert_eq!(
+            Depth::from_env(Some("1"), Some("truecolor"), None),
+            Depth::None
+        );
+        assert_eq!(
+            Depth::from_env(Some(""), Some("24bit"), None),
+            Depth::TrueColor
+        );
+        assert_eq!(
+            Depth::from_env(None, None, Some("xterm-256color")),
+            Depth::Ansi256
+        );
+        assert_eq!(Depth::from_env(None, None, Some("linux")), Depth::Ansi16);
+        assert_eq!(Depth::from_env(None, None, None), Depth::Ansi16);
+    }
+
+    #[test]
+    fn parses_colours() {
+        assert_eq!(parse_rgb("#ff8000"), Ok((255, 128, 0)));
+        assert!(parse_rgb("ff8000").is_err());
+        assert!(parse_rgb("#ff80").is_err());
+        assert!(parse_rgb("#gg8000").is_err());
+    }
+
+    #[test]
+    fn falls_back_to_fewer_colours() {
+        assert_eq!(ansi256((255, 215, 0)), 220);
+        assert_eq!(ansi256((0, 0, 0)), 16);
+        assert_eq!(ansi256((128, 128, 128)), 244);
+        let theme = dark(Depth::Ansi16);
+        assert_eq!(theme.color((250, 10, 10)), Some(Color::LightRed));
+        assert_eq!(theme.color((28, 28, 28)), Some(Color::Black));
+        assert_eq!(dark(Depth::None).color((250, 10, 10)), None);
+    }
+
+    #[test]
+    fn rows_fade_towards_the_background() {
+        let theme = dark(Depth::TrueColor);
+        assert_eq!(
+            theme.row(Some(1)),
+            Style::default().fg(Color::Rgb(235, 235, 235))
+        );
+        // no further than FADE_ROWS
+        assert_eq!(theme.row(Some(4)), theme.row(Some(9)));
+        let light = builtin().remove(1);
+        let Some(Color::Rgb(faded, ..)) = light.row(Some(4)).fg else {
+            panic!("truecolor themes use RGB");
+        };
+        assert!(faded > light.text.0);
+    }
+
+    #[test]
+    fn no_color_uses_styles_only() {
+        let theme = dark(Depth::None);
+        for style in [theme.row(Some(0)), theme.matched(Some(2)), theme.error()] {
+            assert_eq!(style.fg, None);
+        }
+        assert!(theme.row(Some(0)).add_modifier.contains(Modifier::BOLD));
+        assert_eq!(
+            theme.marked(Some(0)),
+            Style::default().add_modifier(Modifier::REVERSED)
+        );
+        assert!(
+            theme
+                .matched(None)
+                .add_modifier
+                .contains(Modifier::UNDERLINED)
+        );
+    }
+}